@@ -0,0 +1,10 @@
+-- pending until the confirmation link is followed, then confirmed
+ALTER TABLE subscriber ADD COLUMN status TEXT NOT NULL DEFAULT 'pending';
+-- SHA-256 of the token in the confirmation link, cleared once confirmed
+ALTER TABLE subscriber ADD COLUMN confirmation_token TEXT;
+ALTER TABLE subscriber ADD COLUMN token_expires_at TIMESTAMPTZ;
+-- When the last confirmation email went out, so sign ups can't be used to
+-- flood someone's inbox
+ALTER TABLE subscriber ADD COLUMN confirmation_sent_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX subscriber_confirmation_token ON subscriber (confirmation_token);
@@ -0,0 +1,4 @@
+-- Unsubscribed addresses are kept with status unsubscribed, and when and
+-- why, instead of deleted
+ALTER TABLE subscriber ADD COLUMN unsubscribed_at TIMESTAMPTZ;
+ALTER TABLE subscriber ADD COLUMN unsubscribe_reason TEXT;
@@ -0,0 +1,22 @@
+-- A post to announce to subscribers, completed once every delivery is done
+CREATE TABLE newsletter (
+    blog_id INT4 PRIMARY KEY REFERENCES blog (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE TABLE newsletter_delivery (
+    id SERIAL PRIMARY KEY,
+    blog_id INT4 NOT NULL REFERENCES newsletter (blog_id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    -- pending, sending, sent or failed. Deliveries left sending by a crash
+    -- may have gone out, so they aren't retried
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT4 NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at TIMESTAMPTZ,
+    UNIQUE (blog_id, email)
+);
+
+CREATE INDEX newsletter_delivery_pending ON newsletter_delivery (blog_id) WHERE status = 'pending';
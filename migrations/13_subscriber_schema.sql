@@ -0,0 +1,37 @@
+-- Rebuilt rather than altered, so the primary key is there from the start
+-- and duplicates are dropped without relying on a physical row id
+CREATE TABLE subscriber_new (
+    id SERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CONSTRAINT subscriber_status CHECK (status IN ('pending', 'confirmed', 'unsubscribed')),
+    confirmation_token TEXT,
+    token_expires_at TIMESTAMPTZ,
+    confirmation_sent_at TIMESTAMPTZ,
+    -- Unknown for addresses that were there before, so those get the time of
+    -- this migration and no confirmed_at
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    confirmed_at TIMESTAMPTZ,
+    unsubscribed_at TIMESTAMPTZ,
+    unsubscribe_reason TEXT,
+    -- Where the address signed up, like the footer form
+    source TEXT
+);
+
+-- One row per address, ignoring case, preferring the most subscribed
+INSERT INTO subscriber_new (
+    email, status, confirmation_token, token_expires_at, confirmation_sent_at,
+    unsubscribed_at, unsubscribe_reason
+)
+SELECT DISTINCT ON (lower(email))
+    email, status, confirmation_token, token_expires_at, confirmation_sent_at,
+    unsubscribed_at, unsubscribe_reason
+FROM subscriber
+WHERE email IS NOT NULL AND trim(email) <> ''
+ORDER BY lower(email), CASE status WHEN 'confirmed' THEN 0 WHEN 'pending' THEN 1 ELSE 2 END;
+
+DROP TABLE subscriber;
+ALTER TABLE subscriber_new RENAME TO subscriber;
+
+CREATE UNIQUE INDEX subscriber_email_key ON subscriber (lower(email));
+CREATE UNIQUE INDEX subscriber_confirmation_token ON subscriber (confirmation_token);
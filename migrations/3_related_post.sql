@@ -0,0 +1,8 @@
+CREATE TABLE related_post (
+    blog_id INT4 REFERENCES blog (id) ON DELETE CASCADE,
+    related_id INT4 REFERENCES blog (id) ON DELETE CASCADE,
+    score REAL NOT NULL,
+    pinned BOOLEAN NOT NULL,
+    position INT4 NOT NULL,
+    PRIMARY KEY (blog_id, related_id)
+);
@@ -0,0 +1,9 @@
+-- Embeddings are plain arrays ranked in Rust, so this runs on CockroachDB and
+-- Postgres without pgvector
+CREATE TABLE blog_chunk (
+    blog_id INT4 REFERENCES blog (id) ON DELETE CASCADE,
+    chunk INT4,
+    content TEXT,
+    embedding FLOAT4[] NOT NULL,
+    PRIMARY KEY (blog_id, chunk)
+);
@@ -0,0 +1 @@
+ALTER TABLE blog ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE blog ADD COLUMN featured BOOLEAN NOT NULL DEFAULT FALSE;
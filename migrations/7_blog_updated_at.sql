@@ -0,0 +1,2 @@
+ALTER TABLE blog ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE blog SET updated_at = date::TIMESTAMPTZ WHERE date IS NOT NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE indexnow_submission (
+    id SERIAL PRIMARY KEY,
+    urls TEXT[] NOT NULL,
+    status INT4,
+    attempts INT4 NOT NULL,
+    error TEXT,
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,18 @@
+CREATE TABLE webmention (
+    id SERIAL PRIMARY KEY,
+    source TEXT NOT NULL,
+    target TEXT NOT NULL,
+    blog_id INT4 NOT NULL REFERENCES blog (id) ON DELETE CASCADE,
+    -- pending, verifying, verified or rejected
+    status TEXT NOT NULL DEFAULT 'pending',
+    title TEXT,
+    -- Verified mentions are only shown once approved
+    approved BOOLEAN NOT NULL DEFAULT FALSE,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    verified_at TIMESTAMPTZ,
+    -- Mentions are verified again when the run that claimed them didn't finish
+    claimed_at TIMESTAMPTZ,
+    UNIQUE (source, target)
+);
+
+CREATE INDEX webmention_blog_id ON webmention (blog_id) WHERE status = 'verified' AND approved;
@@ -1,10 +1,25 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs::read_to_string;
 
+use serde::Deserialize;
 use sqlx::error::Error;
 use sqlx::types::time::Date;
-use sqlx::{Pool, Postgres};
-use time::Month;
+use sqlx::{Pool, Postgres, QueryBuilder};
+use time::{Month, OffsetDateTime};
+
+use crate::utils::embedding::embedding_similarity;
+use crate::utils::pagination::{Cursor, CursorPage, Page, PageRequest};
+use crate::utils::similarity::{cosine_similarity, tag_overlap, tfidf_vectors};
+
+pub const RELATED_POSTS: usize = 3;
+const RELATED_TAG_WEIGHT: f32 = 0.4;
+const RELATED_TEXT_WEIGHT: f32 = 0.6;
+
+const SEARCH_DOCUMENT: &str =
+    "to_tsvector('english', coalesce(title, '') || ' ' || coalesce(body, ''))";
+const SEMANTIC_WEIGHT: f32 = 0.7;
+const MIN_SEMANTIC_SIMILARITY: f32 = 0.3;
 
 #[derive(PartialEq, Debug, sqlx::FromRow)]
 pub struct Blog {
@@ -14,56 +29,419 @@ pub struct Blog {
     pub body: String,
     pub date: Date,
     pub tags: Vec<String>,
+    pub pinned: bool,
+    pub featured: bool,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct RelatedPost {
+    pub blog_id: i32,
+    pub related_id: i32,
+    pub score: f32,
+    pub pinned: bool,
+    pub position: i32,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Relevance,
+    Newest,
+    Oldest,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListOrder {
+    #[default]
+    Newest,
+    Oldest,
+    Title,
+}
+
+impl ListOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListOrder::Newest => "newest",
+            ListOrder::Oldest => "oldest",
+            ListOrder::Title => "title",
+        }
+    }
+
+    pub fn supports_cursor(&self) -> bool {
+        matches!(self, ListOrder::Newest | ListOrder::Oldest)
+    }
+
+    // Rows that come after the cursor ($1, $2, $3) = (pinned, date, id). Pinned
+    // posts always come first, so only the date direction differs per order.
+    fn after_cursor(&self) -> &'static str {
+        match self {
+            ListOrder::Oldest => "(pinned < $1 OR (pinned = $1 AND (date, id) > ($2, $3)))",
+            // Title order has no keyset, callers check `supports_cursor`
+            ListOrder::Newest | ListOrder::Title => "(pinned, date, id) < ($1, $2, $3)",
+        }
+    }
+
+    fn order_by(&self) -> &'static str {
+        match self {
+            ListOrder::Newest => "date DESC, id DESC",
+            ListOrder::Oldest => "date ASC, id ASC",
+            ListOrder::Title => "title ASC, id ASC",
+        }
+    }
+}
+
+/// Posts with a chunk similar enough to the query's embedding, and the
+/// similarity of their closest chunk.
+#[derive(Debug, Default)]
+struct SemanticMatches {
+    ids: Vec<i32>,
+    scores: Vec<f32>,
+}
+
+impl SemanticMatches {
+    async fn find(pool: &Pool<Postgres>, embedding: &[f32]) -> Result<SemanticMatches, Error> {
+        let chunks =
+            sqlx::query_as::<_, (i32, Vec<f32>)>("SELECT blog_id, embedding FROM blog_chunk")
+                .fetch_all(pool)
+                .await?;
+
+        let mut best: HashMap<i32, f32> = HashMap::new();
+        for (blog_id, chunk) in chunks {
+            let score = embedding_similarity(embedding, &chunk);
+            let entry = best.entry(blog_id).or_insert(score);
+            *entry = entry.max(score);
+        }
+
+        let (ids, scores) = best
+            .into_iter()
+            .filter(|(_, score)| *score >= MIN_SEMANTIC_SIMILARITY)
+            .unzip();
+        Ok(SemanticMatches { ids, scores })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub search: String,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub tags: Vec<String>,
+    pub sort: SortOrder,
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl SearchQuery {
+    fn pattern(&self) -> String {
+        format!("%{}%", self.search)
+    }
+
+    // Pushes the WHERE clause shared by the result query and the facet counts
+    fn push_filters<'a>(
+        &'a self,
+        semantic: Option<&'a SemanticMatches>,
+        query: &mut QueryBuilder<'a, Postgres>,
+    ) {
+        query.push(" WHERE date <= CURRENT_DATE AND (title ILIKE ");
+        query.push_bind(self.pattern());
+        query.push(" OR ");
+        query.push(SEARCH_DOCUMENT);
+        query.push(" @@ plainto_tsquery('english', ");
+        query.push_bind(&self.search);
+        query.push(")");
+        if let Some(semantic) = semantic {
+            query.push(" OR id = ANY(");
+            query.push_bind(&semantic.ids);
+            query.push(")");
+        }
+        query.push(")");
+
+        if let Some(from) = self.from {
+            query.push(" AND date >= ");
+            query.push_bind(from);
+        }
+        if let Some(to) = self.to {
+            query.push(" AND date <= ");
+            query.push_bind(to);
+        }
+        if !self.tags.is_empty() {
+            query.push(" AND tags @> ");
+            query.push_bind(&self.tags);
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct ArchiveEntry {
+    pub id: i32,
+    pub title: String,
+    pub date: Date,
+}
+
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct ArchiveCount {
+    pub year: i32,
+    pub month: i32,
+    pub count: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    pub blogs: Vec<Blog>,
+    pub tag_facets: Vec<FacetCount>,
+    pub year_facets: Vec<FacetCount>,
 }
 
 impl Blog {
     pub async fn get_blogs(
         pool: &Pool<Postgres>,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<Blog>, Error> {
-        let query = format!("SELECT * FROM blog LIMIT {} OFFSET {}", limit, offset);
-        let blogs: Vec<Blog> = sqlx::query_as::<_, Blog>(&query).fetch_all(pool).await?;
+        request: PageRequest,
+        order: ListOrder,
+    ) -> Result<Page<Blog>, Error> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM blog WHERE date <= CURRENT_DATE")
+            .fetch_one(pool)
+            .await?;
 
-        let reordered_blogs = Blog::reorder_blogs(blogs);
+        let query = format!(
+            "SELECT * FROM blog WHERE date <= CURRENT_DATE
+            ORDER BY pinned DESC, {} LIMIT $1 OFFSET $2",
+            order.order_by()
+        );
+        let blogs: Vec<Blog> = sqlx::query_as::<_, Blog>(&query)
+            .bind(request.limit())
+            .bind(request.offset())
+            .fetch_all(pool)
+            .await?;
 
-        Ok(reordered_blogs)
+        Ok(Page::new(blogs, request, total))
     }
 
-    fn reorder_blogs(mut blogs: Vec<Blog>) -> Vec<Blog> {
-        if blogs.is_empty() {
-            return blogs;
-        }
+    pub async fn get_blogs_after(
+        pool: &Pool<Postgres>,
+        cursor: Option<Cursor>,
+        per_page: i64,
+        order: ListOrder,
+    ) -> Result<CursorPage<Blog>, Error> {
+        let order = if order.supports_cursor() {
+            order
+        } else {
+            ListOrder::Newest
+        };
+        let order_by = format!("pinned DESC, {}", order.order_by());
 
-        let max_id = blogs.iter().map(|b| b.id).max().unwrap();
+        // Fetch one extra row to find out whether there is a next page
+        let mut blogs: Vec<Blog> = match cursor {
+            Some(cursor) => {
+                let query = format!(
+                    "SELECT * FROM blog WHERE date <= CURRENT_DATE AND {} ORDER BY {} LIMIT $4",
+                    order.after_cursor(),
+                    order_by
+                );
+                sqlx::query_as::<_, Blog>(&query)
+                    .bind(cursor.pinned)
+                    .bind(cursor.date)
+                    .bind(cursor.id)
+                    .bind(per_page + 1)
+                    .fetch_all(pool)
+                    .await?
+            }
+            None => {
+                let query = format!(
+                    "SELECT * FROM blog WHERE date <= CURRENT_DATE ORDER BY {} LIMIT $1",
+                    order_by
+                );
+                sqlx::query_as::<_, Blog>(&query)
+                    .bind(per_page + 1)
+                    .fetch_all(pool)
+                    .await?
+            }
+        };
 
-        blogs.sort_by(|a, b| b.id.cmp(&a.id));
+        let has_next = blogs.len() as i64 > per_page;
+        blogs.truncate(per_page as usize);
+        let next_cursor = has_next
+            .then(|| blogs.last().map(|blog| blog.cursor().encode()))
+            .flatten();
 
-        let (mut evens, mut odds): (Vec<_>, Vec<_>) =
-            blogs.into_iter().partition(|b| b.id % 2 == 0);
-        let mut reordered = Vec::with_capacity(evens.len() + odds.len());
+        Ok(CursorPage {
+            items: blogs,
+            per_page,
+            next_cursor,
+        })
+    }
 
-        if max_id % 2 == 1 {
-            reordered.append(&mut odds);
-            reordered.append(&mut evens);
-        } else {
-            reordered.append(&mut evens);
-            reordered.append(&mut odds);
-        }
+    /// Published posts dated in `[from, to)`, or all of them when no range is
+    /// given, newest first.
+    pub async fn get_archive(
+        pool: &Pool<Postgres>,
+        range: Option<(Date, Date)>,
+    ) -> Result<Vec<ArchiveEntry>, Error> {
+        let (from, to) = range.unzip();
+        sqlx::query_as::<_, ArchiveEntry>(
+            "SELECT id, title, date FROM blog
+            WHERE date <= CURRENT_DATE
+                AND ($1::DATE IS NULL OR date >= $1) AND ($2::DATE IS NULL OR date < $2)
+            ORDER BY date DESC, id DESC",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
 
-        reordered
+    /// Number of published posts per month in `[from, to)`, or for all of
+    /// them, newest first.
+    pub async fn get_archive_counts(
+        pool: &Pool<Postgres>,
+        range: Option<(Date, Date)>,
+    ) -> Result<Vec<ArchiveCount>, Error> {
+        let (from, to) = range.unzip();
+        sqlx::query_as::<_, ArchiveCount>(
+            "SELECT EXTRACT(YEAR FROM date)::INT4 AS year,
+                EXTRACT(MONTH FROM date)::INT4 AS month,
+                COUNT(*) AS count
+            FROM blog
+            WHERE date <= CURRENT_DATE
+                AND ($1::DATE IS NULL OR date >= $1) AND ($2::DATE IS NULL OR date < $2)
+            GROUP BY year, month
+            ORDER BY year DESC, month DESC",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
     }
 
-    pub async fn search_blogs(pool: &Pool<Postgres>, search: &str) -> Result<Vec<Blog>, Error> {
-        let mut blogs: Vec<Blog> =
-            sqlx::query_as::<_, Blog>("SELECT * FROM blog WHERE title ILIKE $1")
-                .bind(format!("%{}%", search))
-                .fetch_all(pool)
-                .await?;
+    /// The `n` most recent published posts that aren't featured. Posts dated
+    /// in the future are scheduled and not published yet.
+    pub async fn get_latest_blogs(pool: &Pool<Postgres>, n: i64) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>(
+            "SELECT * FROM blog
+            WHERE NOT featured AND date <= CURRENT_DATE
+            ORDER BY date DESC, id DESC
+            LIMIT $1",
+        )
+        .bind(n)
+        .fetch_all(pool)
+        .await
+    }
 
-        blogs.sort_by(|a, b| b.id.cmp(&a.id));
+    pub async fn get_featured_blogs(pool: &Pool<Postgres>) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>(
+            "SELECT * FROM blog
+            WHERE featured AND date <= CURRENT_DATE
+            ORDER BY date DESC, id DESC",
+        )
+        .fetch_all(pool)
+        .await
+    }
 
-        Ok(blogs)
+    /// The `n` most recently published posts, or all of them, optionally with a tag.
+    pub async fn get_published_blogs(
+        pool: &Pool<Postgres>,
+        tag: Option<&str>,
+        n: Option<i64>,
+    ) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>(
+            "SELECT * FROM blog
+            WHERE date <= CURRENT_DATE AND ($1::TEXT IS NULL OR $1 = ANY(tags))
+            ORDER BY date DESC, id DESC
+            LIMIT $2",
+        )
+        .bind(tag)
+        .bind(n)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// When the post last changed for readers: its last update, or the start
+    /// of its date if it was scheduled and went live after that.
+    pub fn modified_at(&self) -> OffsetDateTime {
+        self.updated_at.max(self.date.midnight().assume_utc())
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        Cursor {
+            pinned: self.pinned,
+            date: self.date,
+            id: self.id,
+        }
+    }
+
+    pub async fn search_blogs(
+        pool: &Pool<Postgres>,
+        search: &SearchQuery,
+    ) -> Result<SearchResults, Error> {
+        let semantic = match &search.embedding {
+            Some(embedding) => Some(SemanticMatches::find(pool, embedding).await?),
+            None => None,
+        };
+
+        let mut query = QueryBuilder::new("SELECT * FROM blog");
+        search.push_filters(semantic.as_ref(), &mut query);
+
+        match (search.sort, &semantic) {
+            (SortOrder::Relevance, Some(semantic)) => {
+                query.push(" ORDER BY ");
+                query.push_bind(SEMANTIC_WEIGHT);
+                query.push(" * coalesce((SELECT score FROM unnest(");
+                query.push_bind(&semantic.ids);
+                query.push(", ");
+                query.push_bind(&semantic.scores);
+                query.push(") AS semantic (id, score) WHERE semantic.id = blog.id), 0) + ");
+                query.push_bind(1.0 - SEMANTIC_WEIGHT);
+                query.push(" * ts_rank(");
+                query.push(SEARCH_DOCUMENT);
+                query.push(", plainto_tsquery('english', ");
+                query.push_bind(search.search.clone());
+                query.push("), 32) DESC, date DESC, id DESC");
+            }
+            (SortOrder::Relevance, None) => {
+                query.push(" ORDER BY title ILIKE ");
+                query.push_bind(search.pattern());
+                query.push(" DESC, ts_rank(");
+                query.push(SEARCH_DOCUMENT);
+                query.push(", plainto_tsquery('english', ");
+                query.push_bind(search.search.clone());
+                query.push(")) DESC, date DESC, id DESC");
+            }
+            (SortOrder::Newest, _) => {
+                query.push(" ORDER BY date DESC, id DESC");
+            }
+            (SortOrder::Oldest, _) => {
+                query.push(" ORDER BY date ASC, id ASC");
+            }
+        }
+
+        let blogs = query.build_query_as::<Blog>().fetch_all(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            "SELECT tag AS value, COUNT(*) AS count FROM blog CROSS JOIN LATERAL unnest(tags) AS tag",
+        );
+        search.push_filters(semantic.as_ref(), &mut query);
+        query.push(" GROUP BY tag ORDER BY count DESC, tag");
+        let tag_facets = query.build_query_as::<FacetCount>().fetch_all(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            "SELECT EXTRACT(YEAR FROM date)::INT4::TEXT AS value, COUNT(*) AS count FROM blog",
+        );
+        search.push_filters(semantic.as_ref(), &mut query);
+        query.push(" GROUP BY value ORDER BY value DESC");
+        let year_facets = query.build_query_as::<FacetCount>().fetch_all(pool).await?;
+
+        Ok(SearchResults {
+            blogs,
+            tag_facets,
+            year_facets,
+        })
     }
 
     pub fn text_to_html(text: String) -> String {
@@ -87,15 +465,19 @@ impl Blog {
                     body: markdown_body,
                     date: Date::from_calendar_date(2019, Month::January, 1).unwrap(),
                     tags: vec![String::from("hoi")],
+                    pinned: false,
+                    featured: false,
+                    updated_at: OffsetDateTime::now_utc(),
                 };
                 return Ok(markdown_blog);
             }
         }
 
-        let mut blog: Blog = sqlx::query_as::<_, Blog>("SELECT * FROM blog WHERE id = $1")
-            .bind(id)
-            .fetch_one(pool)
-            .await?;
+        let mut blog: Blog =
+            sqlx::query_as::<_, Blog>("SELECT * FROM blog WHERE id = $1 AND date <= CURRENT_DATE")
+                .bind(id)
+                .fetch_one(pool)
+                .await?;
 
         let html = Blog::text_to_html(blog.body);
         blog.body = html;
@@ -103,12 +485,173 @@ impl Blog {
         Ok(blog)
     }
 
+    /// Whether a post exists, published or not.
+    pub async fn blog_exists(pool: &Pool<Postgres>, id: i32) -> Result<bool, Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM blog WHERE id = $1)")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn get_all_blogs(pool: &Pool<Postgres>) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>("SELECT * FROM blog ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn get_related_blogs(pool: &Pool<Postgres>, id: i32) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>(
+            "SELECT blog.* FROM related_post
+            JOIN blog ON blog.id = related_post.related_id
+            WHERE related_post.blog_id = $1 AND blog.date <= CURRENT_DATE
+            ORDER BY related_post.position",
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Ranks the other posts for every post by tag overlap and TF-IDF cosine
+    /// similarity of the bodies. Posts listed in `pins` come first, in the
+    /// given order, and the remaining slots up to `n` are filled by score.
+    pub fn compute_related(
+        blogs: &[Blog],
+        pins: &HashMap<i32, Vec<i32>>,
+        n: usize,
+    ) -> Vec<RelatedPost> {
+        let bodies: Vec<&str> = blogs.iter().map(|b| b.body.as_str()).collect();
+        let vectors = tfidf_vectors(&bodies);
+        let index: HashMap<i32, usize> = blogs.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+
+        let mut related = Vec::new();
+
+        for (i, blog) in blogs.iter().enumerate() {
+            let score = |j: usize| {
+                RELATED_TAG_WEIGHT * tag_overlap(&blog.tags, &blogs[j].tags)
+                    + RELATED_TEXT_WEIGHT * cosine_similarity(&vectors[i], &vectors[j])
+            };
+
+            let mut picked: Vec<(usize, f32, bool)> = Vec::with_capacity(n);
+
+            for pinned_id in pins.get(&blog.id).into_iter().flatten() {
+                match index.get(pinned_id) {
+                    Some(&j) if j != i && !picked.iter().any(|(p, _, _)| *p == j) => {
+                        picked.push((j, score(j), true))
+                    }
+                    Some(_) => {}
+                    None => eprintln!("Blog {} pins unknown related blog {}", blog.id, pinned_id),
+                }
+            }
+
+            let mut candidates: Vec<(usize, f32)> = (0..blogs.len())
+                .filter(|&j| j != i && !picked.iter().any(|(p, _, _)| *p == j))
+                .map(|j| (j, score(j)))
+                .filter(|(_, s)| *s > 0.0)
+                .collect();
+            candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(blogs[b.0].id.cmp(&blogs[a.0].id)));
+
+            picked.extend(candidates.into_iter().map(|(j, s)| (j, s, false)));
+            picked.truncate(n.max(pins.get(&blog.id).map_or(0, Vec::len)));
+
+            related.extend(
+                picked
+                    .into_iter()
+                    .enumerate()
+                    .map(|(position, (j, score, pinned))| RelatedPost {
+                        blog_id: blog.id,
+                        related_id: blogs[j].id,
+                        score,
+                        pinned,
+                        position: position as i32,
+                    }),
+            );
+        }
+
+        related
+    }
+
+    pub async fn store_related(
+        pool: &Pool<Postgres>,
+        related: &[RelatedPost],
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("DELETE FROM related_post")
+            .execute(&mut *tx)
+            .await?;
+
+        for post in related {
+            sqlx::query(
+                "INSERT INTO related_post (blog_id, related_id, score, pinned, position)
+                VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(post.blog_id)
+            .bind(post.related_id)
+            .bind(post.score)
+            .bind(post.pinned)
+            .bind(post.position)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    pub async fn get_blogs_without_chunks(pool: &Pool<Postgres>) -> Result<Vec<Blog>, Error> {
+        sqlx::query_as::<_, Blog>(
+            "SELECT * FROM blog
+            WHERE NOT EXISTS (SELECT 1 FROM blog_chunk WHERE blog_chunk.blog_id = blog.id)
+            ORDER BY id",
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Drops a post's chunks, so the next ingest embeds its new content.
+    pub async fn delete_chunks(pool: &Pool<Postgres>, id: i32) -> Result<(), Error> {
+        sqlx::query("DELETE FROM blog_chunk WHERE blog_id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn store_chunks(
+        pool: &Pool<Postgres>,
+        id: i32,
+        chunks: &[String],
+        embeddings: &[Vec<f32>],
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("DELETE FROM blog_chunk WHERE blog_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        for (chunk, (content, embedding)) in chunks.iter().zip(embeddings).enumerate() {
+            sqlx::query(
+                "INSERT INTO blog_chunk (blog_id, chunk, content, embedding)
+                VALUES ($1, $2, $3, $4)",
+            )
+            .bind(id)
+            .bind(chunk as i32)
+            .bind(content)
+            .bind(embedding)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
     pub async fn create_blog(&self, pool: &Pool<Postgres>) -> Result<(), Error> {
         let tags_array: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
 
         let result = sqlx::query(
-            "INSERT INTO blog (id, title, summary, body, date, tags)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            "INSERT INTO blog (id, title, summary, body, date, tags, pinned, featured)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             ON CONFLICT (id) DO NOTHING",
         )
         .bind(&self.id)
@@ -117,6 +660,8 @@ impl Blog {
         .bind(&self.body)
         .bind(&self.date)
         .bind(&tags_array)
+        .bind(self.pinned)
+        .bind(self.featured)
         .execute(pool)
         .await?;
 
@@ -124,11 +669,57 @@ impl Blog {
 
         Ok(())
     }
+
+    /// Updates a post from its article, bumping `updated_at` only when the
+    /// content changed, which is what it returns. The summary is maintained
+    /// in the database.
+    pub async fn update_blog(&self, pool: &Pool<Postgres>) -> Result<bool, Error> {
+        let tags_array: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
+
+        let changed = sqlx::query_scalar::<_, bool>(
+            "UPDATE blog SET title = $2, body = $3, date = $4, tags = $5, pinned = $6,
+                featured = $7,
+                updated_at = CASE
+                    WHEN (title, body, date, tags) IS DISTINCT FROM ($2, $3, $4, $5) THEN now()
+                    ELSE updated_at
+                END
+            WHERE id = $1
+            RETURNING updated_at = now()",
+        )
+        .bind(self.id)
+        .bind(&self.title)
+        .bind(&self.body)
+        .bind(self.date)
+        .bind(&tags_array)
+        .bind(self.pinned)
+        .bind(self.featured)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(changed.unwrap_or(false))
+    }
+}
+
+/// A published post for tests to adjust with struct update syntax
+#[cfg(test)]
+pub fn test_blog(id: i32) -> Blog {
+    Blog {
+        id,
+        title: format!("Title {}", id),
+        summary: format!("Summary {}", id),
+        body: String::new(),
+        date: time::macros::date!(2024 - 01 - 01),
+        tags: vec![],
+        pinned: false,
+        featured: false,
+        updated_at: time::macros::datetime!(2024-01-01 0:00 UTC),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::utils::db::get_db;
+    use crate::utils::pagination::MAX_PER_PAGE;
 
     use super::*;
 
@@ -136,72 +727,221 @@ mod tests {
     #[tokio::test]
     async fn test_search() {
         let pool = get_db().await;
-        let result = Blog::search_blogs(&pool, "hoi").await.unwrap();
-        assert_eq!(result.len() > 0, true);
-        assert_eq!(result[0].id, 420);
+        let query = SearchQuery {
+            search: "hoi".to_string(),
+            ..Default::default()
+        };
+        let result = Blog::search_blogs(&pool, &query).await.unwrap();
+        assert_eq!(result.blogs.len() > 0, true);
+        assert_eq!(result.blogs[0].id, 420);
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        use crate::utils::embedding::{Embedder, HashingEmbedder};
+
+        let pool = get_db().await;
+        let blogs = Blog::get_all_blogs(&pool).await.unwrap();
+        for blog in &blogs {
+            let chunks = vec![blog.body.clone()];
+            let embeddings = HashingEmbedder.embed(&chunks).await.unwrap();
+            Blog::store_chunks(&pool, blog.id, &chunks, &embeddings)
+                .await
+                .unwrap();
+        }
+
+        let target = &blogs[0];
+        let embedding = HashingEmbedder
+            .embed(std::slice::from_ref(&target.body))
+            .await
+            .unwrap()
+            .pop();
+        let query = SearchQuery {
+            search: "zzzz-no-keyword-match".to_string(),
+            embedding,
+            ..Default::default()
+        };
+        let result = Blog::search_blogs(&pool, &query).await.unwrap();
+        assert_eq!(result.blogs[0].id, target.id);
+    }
+
+    #[tokio::test]
+    async fn test_search_filters() {
+        let pool = get_db().await;
+        let from = Date::from_calendar_date(2023, Month::January, 1).unwrap();
+        let to = Date::from_calendar_date(2024, Month::December, 31).unwrap();
+        let query = SearchQuery {
+            search: String::new(),
+            from: Some(from),
+            to: Some(to),
+            tags: vec!["devops".to_string()],
+            sort: SortOrder::Oldest,
+            embedding: None,
+        };
+        let result = Blog::search_blogs(&pool, &query).await.unwrap();
+
+        assert!(result
+            .blogs
+            .iter()
+            .all(|b| b.date >= from && b.date <= to && b.tags.contains(&"devops".to_string())));
+        assert!(result.blogs.windows(2).all(|w| w[0].date <= w[1].date));
+        assert!(result
+            .year_facets
+            .iter()
+            .all(|f| f.value == "2023" || f.value == "2024"));
+        let devops = result.tag_facets.iter().find(|f| f.value == "devops");
+        assert_eq!(devops.map_or(0, |f| f.count), result.blogs.len() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_get_blogs_order() {
+        let pool = get_db().await;
+        let request = PageRequest::new(1, MAX_PER_PAGE).unwrap();
+        let page = Blog::get_blogs(&pool, request, ListOrder::Newest)
+            .await
+            .unwrap();
+
+        assert!(page.items.windows(2).all(|w| {
+            (w[0].pinned && !w[1].pinned)
+                || (w[0].pinned == w[1].pinned && (w[0].date, w[0].id) >= (w[1].date, w[1].id))
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_get_blogs_after() {
+        let pool = get_db().await;
+        for order in [ListOrder::Newest, ListOrder::Oldest] {
+            let request = PageRequest::new(1, MAX_PER_PAGE).unwrap();
+            let all = Blog::get_blogs(&pool, request, order).await.unwrap().items;
+
+            let mut walked = vec![];
+            let mut cursor = None;
+            loop {
+                let page = Blog::get_blogs_after(&pool, cursor, 3, order)
+                    .await
+                    .unwrap();
+                walked.extend(page.items.into_iter().map(|b| b.id));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(Cursor::decode(&next).unwrap()),
+                    None => break,
+                }
+            }
+
+            let ids: Vec<i32> = all.iter().map(|b| b.id).collect();
+            assert_eq!(walked, ids);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive() {
+        let pool = get_db().await;
+        let from = Date::from_calendar_date(2023, Month::January, 1).unwrap();
+        let to = Date::from_calendar_date(2024, Month::January, 1).unwrap();
+
+        let entries = Blog::get_archive(&pool, Some((from, to))).await.unwrap();
+        let counts = Blog::get_archive_counts(&pool, Some((from, to)))
+            .await
+            .unwrap();
+
+        assert!(entries.iter().all(|e| e.date >= from && e.date < to));
+        assert!(counts.iter().all(|c| c.year == 2023));
+        assert_eq!(
+            counts.iter().map(|c| c.count).sum::<i64>(),
+            entries.len() as i64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_blogs() {
+        let pool = get_db().await;
+        let today = time::OffsetDateTime::now_utc().date();
+
+        let latest = Blog::get_latest_blogs(&pool, 3).await.unwrap();
+
+        assert!(latest.len() <= 3);
+        assert!(latest
+            .iter()
+            .all(|blog| !blog.featured && blog.date <= today));
+        assert!(latest.windows(2).all(|w| w[0].date >= w[1].date));
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_scheduled_hidden() {
+        let pool = get_db().await;
+        let id = 990_035;
+        let scheduled = Blog {
+            title: String::from("Scheduled zebracorn"),
+            body: String::from("zebracorn"),
+            date: OffsetDateTime::now_utc().date() + time::Duration::days(30),
+            pinned: true,
+            ..test_blog(id)
+        };
+        scheduled.create_blog(&pool).await.unwrap();
+
+        let request = PageRequest::new(1, MAX_PER_PAGE).unwrap();
+        let page = Blog::get_blogs(&pool, request, ListOrder::Newest)
+            .await
+            .unwrap();
+        let after = Blog::get_blogs_after(&pool, None, MAX_PER_PAGE, ListOrder::Newest)
+            .await
+            .unwrap();
+        let archive = Blog::get_archive(&pool, None).await.unwrap();
+        let query = SearchQuery {
+            search: String::from("zebracorn"),
+            ..Default::default()
+        };
+        let search = Blog::search_blogs(&pool, &query).await.unwrap();
+        let exists = Blog::blog_exists(&pool, id).await.unwrap();
+        let found = Blog::get_blog(&pool, id).await;
+
+        sqlx::query("DELETE FROM blog WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(page.items.iter().all(|b| b.id != id));
+        assert!(after.items.iter().all(|b| b.id != id));
+        assert!(archive.iter().all(|e| e.id != id));
+        assert!(search.blogs.is_empty());
+        assert!(exists);
+        assert!(found.is_err());
     }
 
     #[test]
-    fn test_reorder() {
+    fn test_compute_related() {
+        let blog = |id: i32, body: &str, tags: &[&str]| Blog {
+            body: body.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..test_blog(id)
+        };
         let blogs = vec![
-            Blog {
-                id: 7,
-                title: "Title 7".to_string(),
-                summary: "Summary 7".to_string(),
-                body: "Body 7".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag1".to_string()],
-            },
-            Blog {
-                id: 6,
-                title: "Title 6".to_string(),
-                summary: "Summary 6".to_string(),
-                body: "Body 6".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag2".to_string()],
-            },
-            Blog {
-                id: 5,
-                title: "Title 5".to_string(),
-                summary: "Summary 5".to_string(),
-                body: "Body 5".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag3".to_string()],
-            },
-            Blog {
-                id: 4,
-                title: "Title 4".to_string(),
-                summary: "Summary 4".to_string(),
-                body: "Body 4".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag4".to_string()],
-            },
-            Blog {
-                id: 3,
-                title: "Title 3".to_string(),
-                summary: "Summary 3".to_string(),
-                body: "Body 3".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag5".to_string()],
-            },
-            Blog {
-                id: 2,
-                title: "Title 2".to_string(),
-                summary: "Summary 2".to_string(),
-                body: "Body 2".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag6".to_string()],
-            },
-            Blog {
-                id: 1,
-                title: "Title 1".to_string(),
-                summary: "Summary 1".to_string(),
-                body: "Body 1".to_string(),
-                date: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
-                tags: vec!["tag7".to_string()],
-            },
+            blog(1, "nix flakes for reproducible python builds", &["nix"]),
+            blog(
+                2,
+                "reproducible python environments with nix",
+                &["nix", "devops"],
+            ),
+            blog(3, "building a split ergonomic keyboard", &["hardware"]),
+            blog(4, "gitlab pipelines for databricks bundles", &["devops"]),
         ];
-        let reordered_blogs = Blog::reorder_blogs(blogs);
-        assert_eq!(reordered_blogs[1].id, 6)
+
+        let related = Blog::compute_related(&blogs, &HashMap::new(), 2);
+        let for_1: Vec<i32> = related
+            .iter()
+            .filter(|r| r.blog_id == 1)
+            .map(|r| r.related_id)
+            .collect();
+        assert_eq!(for_1, vec![2]);
+
+        let pins = HashMap::from([(1, vec![3])]);
+        let related = Blog::compute_related(&blogs, &pins, 2);
+        let for_1: Vec<&RelatedPost> = related.iter().filter(|r| r.blog_id == 1).collect();
+        assert_eq!(for_1[0].related_id, 3);
+        assert!(for_1[0].pinned);
+        assert_eq!(for_1[1].related_id, 2);
+        assert!(!for_1[1].pinned);
     }
 }
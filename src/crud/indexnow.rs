@@ -0,0 +1,108 @@
+use sqlx::error::Error;
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{Pool, Postgres};
+
+use crate::utils::indexnow::Submission;
+
+/// Keeps a log of what was sent to IndexNow and how it went.
+pub async fn record_submission(
+    pool: &Pool<Postgres>,
+    submission: &Submission,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO indexnow_submission (urls, status, attempts, error)
+        VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&submission.urls)
+    .bind(submission.status.map(i32::from))
+    .bind(submission.attempts as i32)
+    .bind(&submission.error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Those of `urls` that weren't submitted successfully since the matching
+/// time in `modified`.
+pub async fn get_unsubmitted(
+    pool: &Pool<Postgres>,
+    urls: &[String],
+    modified: &[OffsetDateTime],
+) -> Result<Vec<String>, Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT url FROM unnest($1::TEXT[], $2::TIMESTAMPTZ[]) AS post (url, modified_at)
+        WHERE NOT EXISTS (
+            SELECT 1 FROM indexnow_submission
+            WHERE error IS NULL AND post.url = ANY(urls) AND submitted_at >= post.modified_at
+        )
+        ORDER BY url",
+    )
+    .bind(urls)
+    .bind(modified)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::db::get_db;
+
+    use super::*;
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_record_submission() {
+        let pool = get_db().await;
+        let url = String::from("https://www.example.com/blog/test-record-submission");
+        let submission = Submission {
+            urls: vec![url.clone()],
+            status: Some(429),
+            attempts: 4,
+            error: Some(String::from("endpoint responded with 429")),
+        };
+        record_submission(&pool, &submission).await.unwrap();
+
+        let (status, attempts): (Option<i32>, i32) =
+            sqlx::query_as("SELECT status, attempts FROM indexnow_submission WHERE $1 = ANY(urls)")
+                .bind(&url)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((status, attempts), (Some(429), 4));
+        let other = String::from("https://www.example.com/blog/test-unsubmitted");
+        let both = [url.clone(), other.clone()];
+        let modified = [OffsetDateTime::now_utc() - time::Duration::hours(1); 2];
+        // Failed submissions are tried again
+        assert_eq!(
+            get_unsubmitted(&pool, &both, &modified)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let submission = Submission {
+            urls: vec![url.clone()],
+            status: Some(200),
+            attempts: 1,
+            error: None,
+        };
+        record_submission(&pool, &submission).await.unwrap();
+        assert_eq!(
+            get_unsubmitted(&pool, &both, &modified).await.unwrap(),
+            vec![other]
+        );
+        // Changed since
+        let changed = [OffsetDateTime::now_utc() + time::Duration::hours(1); 2];
+        assert_eq!(
+            get_unsubmitted(&pool, &both, &changed).await.unwrap().len(),
+            2
+        );
+
+        sqlx::query("DELETE FROM indexnow_submission WHERE $1 = ANY(urls)")
+            .bind(&url)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
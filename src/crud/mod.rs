@@ -1 +1,5 @@
 pub mod blog;
+pub mod indexnow;
+pub mod newsletter;
+pub mod subscriber;
+pub mod webmention;
@@ -0,0 +1,132 @@
+use sqlx::error::Error;
+use sqlx::{Pool, Postgres};
+
+/// One subscriber's copy of a newsletter.
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct Delivery {
+    pub id: i32,
+    pub email: String,
+}
+
+pub struct Newsletter;
+
+impl Newsletter {
+    /// Queues a newsletter for a post. Returns whether it wasn't queued yet.
+    pub async fn enqueue(pool: &Pool<Postgres>, blog_id: i32) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "INSERT INTO newsletter (blog_id) VALUES ($1)
+            ON CONFLICT (blog_id) DO NOTHING",
+        )
+        .bind(blog_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Posts with a newsletter that isn't completed, once they're published.
+    pub async fn get_due(pool: &Pool<Postgres>) -> Result<Vec<i32>, Error> {
+        sqlx::query_scalar::<_, i32>(
+            "SELECT newsletter.blog_id FROM newsletter
+            JOIN blog ON blog.id = newsletter.blog_id
+            WHERE newsletter.completed_at IS NULL AND blog.date <= CURRENT_DATE
+            ORDER BY blog.date",
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Adds a pending delivery for every confirmed subscriber who doesn't
+    /// have one yet. Returns how many were added.
+    pub async fn add_deliveries(pool: &Pool<Postgres>, blog_id: i32) -> Result<u64, Error> {
+        let result = sqlx::query(
+            "INSERT INTO newsletter_delivery (blog_id, email)
+            SELECT $1, email FROM subscriber WHERE status = 'confirmed'
+            ON CONFLICT (blog_id, email) DO NOTHING",
+        )
+        .bind(blog_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Queues failed deliveries again. Returns how many there were.
+    pub async fn retry_failed(pool: &Pool<Postgres>, blog_id: i32) -> Result<u64, Error> {
+        let result = sqlx::query(
+            "UPDATE newsletter_delivery SET status = 'pending'
+            WHERE blog_id = $1 AND status = 'failed'",
+        )
+        .bind(blog_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Claims the next pending delivery to a still confirmed subscriber.
+    /// Claimed deliveries are skipped by concurrent senders, and aren't sent
+    /// again when the sender crashes before marking them.
+    pub async fn claim_next(
+        pool: &Pool<Postgres>,
+        blog_id: i32,
+    ) -> Result<Option<Delivery>, Error> {
+        sqlx::query_as::<_, Delivery>(
+            "UPDATE newsletter_delivery SET status = 'sending', attempts = attempts + 1
+            WHERE id = (
+                SELECT delivery.id FROM newsletter_delivery AS delivery
+                JOIN subscriber ON subscriber.email = delivery.email
+                WHERE delivery.blog_id = $1 AND delivery.status = 'pending'
+                    AND subscriber.status = 'confirmed'
+                ORDER BY delivery.id
+                LIMIT 1
+                FOR UPDATE OF delivery SKIP LOCKED
+            )
+            RETURNING id, email",
+        )
+        .bind(blog_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn mark_sent(pool: &Pool<Postgres>, id: i32) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE newsletter_delivery SET status = 'sent', error = NULL, sent_at = now()
+            WHERE id = $1",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_failed(pool: &Pool<Postgres>, id: i32, error: &str) -> Result<(), Error> {
+        sqlx::query("UPDATE newsletter_delivery SET status = 'failed', error = $2 WHERE id = $1")
+            .bind(id)
+            .bind(error)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Completes the newsletter when nothing is pending anymore, including
+    /// deliveries to addresses that unsubscribed in the meantime.
+    pub async fn complete(pool: &Pool<Postgres>, blog_id: i32) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "UPDATE newsletter SET completed_at = now()
+            WHERE blog_id = $1 AND completed_at IS NULL AND NOT EXISTS (
+                SELECT 1 FROM newsletter_delivery AS delivery
+                JOIN subscriber ON subscriber.email = delivery.email
+                WHERE delivery.blog_id = $1 AND delivery.status = 'pending'
+                    AND subscriber.status = 'confirmed'
+            )",
+        )
+        .bind(blog_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
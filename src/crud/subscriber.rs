@@ -1,40 +1,146 @@
 use sqlx::error::Error;
+use sqlx::types::time::OffsetDateTime;
 use sqlx::{Pool, Postgres};
+use time::Duration;
 
 #[derive(PartialEq, Debug, sqlx::FromRow)]
 pub struct Subscriber {
     email: String,
+    status: String,
+    confirmed_at: Option<OffsetDateTime>,
+    pub source: Option<String>,
+}
+
+/// How long before another confirmation email goes to the same address.
+const RESEND_AFTER: Duration = Duration::minutes(10);
+
+/// What signing up did to the address.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SignUp {
+    New,
+    /// Signed up before without confirming, has a new token now.
+    Pending,
+    /// Confirmed already, left as it was.
+    Existing,
+    /// Unsubscribed before, pending again.
+    Resubscribed,
+    /// Got a confirmation email within `RESEND_AFTER`, left as it was.
+    Throttled,
+}
+
+impl SignUp {
+    pub fn needs_confirmation(self) -> bool {
+        !matches!(self, SignUp::Existing | SignUp::Throttled)
+    }
 }
 
 impl Subscriber {
-    pub async fn create_subscriber(pool: &Pool<Postgres>, email: &str) -> Result<(), Error> {
-        let existing_subscriber =
-            sqlx::query!("SELECT email FROM subscriber WHERE email = $1", email)
-                .fetch_optional(pool)
-                .await?;
+    /// Stores the address as pending with a new confirmation token, unless
+    /// it's confirmed already or got a confirmation email recently. A single
+    /// upsert, so concurrent sign ups of the same address, in any case, end
+    /// up as one subscriber.
+    pub async fn create_subscriber(
+        pool: &Pool<Postgres>,
+        email: &str,
+        token_hash: &str,
+        expires_at: OffsetDateTime,
+        source: &str,
+    ) -> Result<SignUp, Error> {
+        let (previous, upserted) = sqlx::query_as::<_, (Option<String>, bool)>(
+            "WITH previous AS (SELECT status FROM subscriber WHERE lower(email) = lower($1)),
+            upserted AS (
+                INSERT INTO subscriber
+                    (email, confirmation_token, token_expires_at, confirmation_sent_at, source)
+                VALUES ($1, $2, $3, now(), $4)
+                ON CONFLICT (lower(email)) DO UPDATE
+                SET status = 'pending', confirmation_token = EXCLUDED.confirmation_token,
+                    token_expires_at = EXCLUDED.token_expires_at,
+                    confirmation_sent_at = EXCLUDED.confirmation_sent_at
+                WHERE subscriber.status <> 'confirmed'
+                    AND (subscriber.confirmation_sent_at IS NULL
+                        OR subscriber.confirmation_sent_at < $5)
+                RETURNING 1
+            )
+            SELECT (SELECT status FROM previous), EXISTS (SELECT 1 FROM upserted)",
+        )
+        .bind(email)
+        .bind(token_hash)
+        .bind(expires_at)
+        .bind(source)
+        .bind(OffsetDateTime::now_utc() - RESEND_AFTER)
+        .fetch_one(pool)
+        .await?;
 
-        if existing_subscriber.is_some() {
-            println!("Subscriber with email {} already exists", email);
-            return Ok(());
-        }
+        let sign_up = match (previous.as_deref(), upserted) {
+            (None, true) => SignUp::New,
+            (Some("unsubscribed"), true) => SignUp::Resubscribed,
+            (_, true) => SignUp::Pending,
+            (Some("confirmed"), false) => SignUp::Existing,
+            // Including sign ups that lost the race to create the address
+            (_, false) => SignUp::Throttled,
+        };
+        println!("Subscriber with email {} signed up: {:?}", email, sign_up);
+        Ok(sign_up)
+    }
 
+    /// Lets the address get another confirmation email right away, for when
+    /// sending the one with this token failed.
+    pub async fn confirmation_failed(
+        pool: &Pool<Postgres>,
+        email: &str,
+        token_hash: &str,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE subscriber SET confirmation_sent_at = NULL
+            WHERE lower(email) = lower($1) AND confirmation_token = $2",
+        )
+        .bind(email)
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Confirms the subscriber with an unexpired token and returns their
+    /// address. Tokens only work once.
+    pub async fn confirm(pool: &Pool<Postgres>, token_hash: &str) -> Result<Option<String>, Error> {
+        sqlx::query_scalar::<_, String>(
+            "UPDATE subscriber
+            SET status = 'confirmed', confirmed_at = now(), confirmation_token = NULL,
+                token_expires_at = NULL, unsubscribed_at = NULL, unsubscribe_reason = NULL
+            WHERE confirmation_token = $1 AND token_expires_at > now() AND status = 'pending'
+            RETURNING email",
+        )
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Marks the address as unsubscribed. Returns whether it was still
+    /// subscribed, repeated requests keep the first time and reason.
+    pub async fn unsubscribe(
+        pool: &Pool<Postgres>,
+        email: &str,
+        reason: Option<&str>,
+    ) -> Result<bool, Error> {
         let result = sqlx::query(
-            "INSERT INTO subscriber (email)
-			VALUES ($1)",
+            "UPDATE subscriber
+            SET status = 'unsubscribed', unsubscribed_at = now(), unsubscribe_reason = $2,
+                confirmation_token = NULL, token_expires_at = NULL
+            WHERE lower(email) = lower($1) AND status <> 'unsubscribed'",
         )
         .bind(email)
+        .bind(reason)
         .execute(pool)
         .await?;
 
-        match result.rows_affected() {
-            1 => {
-                println!("Subscriber with email {} created", email);
-                Ok(())
-            }
-            _ => Err(Error::RowNotFound),
+        if result.rows_affected() == 1 {
+            println!("Subscriber with email {} unsubscribed", email);
         }
+        Ok(result.rows_affected() == 1)
     }
 
+    #[cfg(test)]
     pub async fn get_subscriber(pool: &Pool<Postgres>, email: &str) -> Result<Subscriber, Error> {
         let subscriber: Subscriber =
             sqlx::query_as::<_, Subscriber>("SELECT * FROM subscriber WHERE email = $1")
@@ -44,6 +150,7 @@ impl Subscriber {
         Ok(subscriber)
     }
 
+    #[cfg(test)]
     pub async fn get_subscribers(
         pool: &Pool<Postgres>,
         email: &str,
@@ -59,6 +166,21 @@ impl Subscriber {
         Ok(subscribers)
     }
 
+    /// Pretends the last confirmation email went out long ago.
+    #[cfg(test)]
+    pub async fn backdate_confirmation(pool: &Pool<Postgres>, email: &str) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE subscriber SET confirmation_sent_at = now() - INTERVAL '1 day'
+            WHERE email = $1",
+        )
+        .bind(email)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(test)]
     pub async fn delete_subscriber(pool: &Pool<Postgres>, email: &str) -> Result<(), Error> {
         let result = sqlx::query("DELETE FROM subscriber WHERE email = $1")
             .bind(email)
@@ -73,17 +195,124 @@ impl Subscriber {
 
 #[cfg(test)]
 mod tests {
+    use time::Duration;
+
     use crate::utils::db::get_db;
 
     use super::*;
 
+    #[ignore]
     #[tokio::test]
     async fn test_subscriber() {
         let pool = get_db().await;
         let email = "hoi@hoi.hoi";
-        Subscriber::create_subscriber(&pool, email).await.unwrap();
+        let tomorrow = OffsetDateTime::now_utc() + Duration::days(1);
+        let sign_up = |email, token| {
+            let pool = pool.clone();
+            async move {
+                Subscriber::create_subscriber(&pool, email, token, tomorrow, "test")
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(sign_up(email, "first").await, SignUp::New);
         let res = Subscriber::get_subscriber(&pool, email).await.unwrap();
         assert_eq!(res.email, email);
+        assert_eq!(res.status, "pending");
+        assert_eq!(res.source.as_deref(), Some("test"));
+        assert_eq!(res.confirmed_at, None);
+
+        // Signing up again right away sends nothing, later it replaces the
+        // token, in any case
+        assert_eq!(sign_up(email, "throttled").await, SignUp::Throttled);
+        assert!(!SignUp::Throttled.needs_confirmation());
+        Subscriber::backdate_confirmation(&pool, email)
+            .await
+            .unwrap();
+        assert_eq!(sign_up("Hoi@Hoi.hoi", "second").await, SignUp::Pending);
+        assert_eq!(
+            Subscriber::get_subscribers(&pool, email)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(Subscriber::confirm(&pool, "first").await.unwrap(), None);
+        assert_eq!(
+            Subscriber::confirm(&pool, "second")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(email)
+        );
+        assert_eq!(Subscriber::confirm(&pool, "second").await.unwrap(), None);
+        let res = Subscriber::get_subscriber(&pool, email).await.unwrap();
+        assert!(res.confirmed_at.is_some());
+        assert_eq!(sign_up(email, "third").await, SignUp::Existing);
+        assert!(!SignUp::Existing.needs_confirmation());
+
+        assert!(
+            Subscriber::unsubscribe(&pool, "HOI@hoi.hoi", Some("too many emails"))
+                .await
+                .unwrap()
+        );
+        assert!(!Subscriber::unsubscribe(&pool, email, None).await.unwrap());
+        let res = Subscriber::get_subscriber(&pool, email).await.unwrap();
+        assert_eq!(res.status, "unsubscribed");
+
+        // Subscribing again needs a new confirmation
+        Subscriber::backdate_confirmation(&pool, email)
+            .await
+            .unwrap();
+        assert_eq!(sign_up(email, "fourth").await, SignUp::Resubscribed);
+        assert_eq!(
+            Subscriber::confirm(&pool, "fourth")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(email)
+        );
+        Subscriber::delete_subscriber(&pool, email).await.unwrap();
+
+        let yesterday = OffsetDateTime::now_utc() - Duration::days(1);
+        Subscriber::create_subscriber(&pool, email, "expired", yesterday, "test")
+            .await
+            .unwrap();
+        assert_eq!(Subscriber::confirm(&pool, "expired").await.unwrap(), None);
+        Subscriber::delete_subscriber(&pool, email).await.unwrap();
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_concurrent_sign_ups() {
+        let pool = get_db().await;
+        let email = "race@hoi.hoi";
+        let expires_at = OffsetDateTime::now_utc() + Duration::days(1);
+        let sign_ups = (0..8).map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let token = format!("race-{}", i);
+                Subscriber::create_subscriber(&pool, email, &token, expires_at, "test")
+                    .await
+                    .unwrap()
+            })
+        });
+        let mut results = Vec::new();
+        for sign_up in sign_ups.collect::<Vec<_>>() {
+            results.push(sign_up.await.unwrap());
+        }
+
+        assert_eq!(results.iter().filter(|r| **r == SignUp::New).count(), 1);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, SignUp::New | SignUp::Throttled)));
+        assert_eq!(
+            Subscriber::get_subscribers(&pool, email)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
         Subscriber::delete_subscriber(&pool, email).await.unwrap();
     }
 }
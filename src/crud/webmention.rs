@@ -0,0 +1,220 @@
+use reqwest::Url;
+use sqlx::error::Error;
+use sqlx::{Pool, Postgres};
+use time::OffsetDateTime;
+
+#[derive(PartialEq, Debug, sqlx::FromRow)]
+pub struct Webmention {
+    pub id: i32,
+    pub source: String,
+    pub target: String,
+    pub blog_id: i32,
+    pub status: String,
+    pub title: Option<String>,
+    pub approved: bool,
+    pub received_at: OffsetDateTime,
+    pub verified_at: Option<OffsetDateTime>,
+    pub claimed_at: Option<OffsetDateTime>,
+}
+
+impl Webmention {
+    /// Queues a mention for verification. A mention that was sent before is
+    /// verified and moderated again, as its source may have changed.
+    pub async fn enqueue(
+        pool: &Pool<Postgres>,
+        source: &str,
+        target: &str,
+        blog_id: i32,
+    ) -> Result<i32, Error> {
+        sqlx::query_scalar(
+            "INSERT INTO webmention (source, target, blog_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (source, target)
+            DO UPDATE SET status = 'pending', approved = FALSE, received_at = now()
+            RETURNING id",
+        )
+        .bind(source)
+        .bind(target)
+        .bind(blog_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Claims the oldest pending mention, or one whose verification was
+    /// claimed long ago and never finished. Claimed mentions are skipped by
+    /// concurrent runs.
+    pub async fn claim_next(pool: &Pool<Postgres>) -> Result<Option<Webmention>, Error> {
+        sqlx::query_as::<_, Webmention>(
+            "UPDATE webmention SET status = 'verifying', claimed_at = now()
+            WHERE id = (
+                SELECT id FROM webmention
+                WHERE status = 'pending'
+                    OR (status = 'verifying' AND claimed_at < now() - INTERVAL '15 minutes')
+                ORDER BY received_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *",
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Queues a claimed mention again, after a failure worth retrying.
+    pub async fn release(pool: &Pool<Postgres>, id: i32) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE webmention SET status = 'pending' WHERE id = $1 AND status = 'verifying'",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn mark_verified(
+        pool: &Pool<Postgres>,
+        id: i32,
+        title: Option<&str>,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE webmention SET status = 'verified', title = $2, verified_at = now()
+            WHERE id = $1",
+        )
+        .bind(id)
+        .bind(title)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Hides a mention whose source doesn't (or no longer) link to the target.
+    pub async fn mark_rejected(pool: &Pool<Postgres>, id: i32) -> Result<(), Error> {
+        sqlx::query("UPDATE webmention SET status = 'rejected', verified_at = now() WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Verified mentions of a post that passed moderation, oldest first.
+    pub async fn get_approved(
+        pool: &Pool<Postgres>,
+        blog_id: i32,
+    ) -> Result<Vec<Webmention>, Error> {
+        sqlx::query_as::<_, Webmention>(
+            "SELECT * FROM webmention
+            WHERE blog_id = $1 AND status = 'verified' AND approved
+            ORDER BY verified_at",
+        )
+        .bind(blog_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// The title of the source page, or its host when it has none.
+    pub fn display_name(&self) -> String {
+        match &self.title {
+            Some(title) if !title.trim().is_empty() => title.trim().to_string(),
+            _ => Url::parse(&self.source)
+                .ok()
+                .and_then(|url| url.host_str().map(String::from))
+                .unwrap_or_else(|| self.source.clone()),
+        }
+    }
+
+    pub fn date(&self) -> time::Date {
+        self.verified_at.unwrap_or(self.received_at).date()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{response::Html, routing::get, Router};
+
+    use crate::crud::blog::test_blog;
+    use crate::utils::db::get_db;
+    use crate::utils::webmention::{verify, WebmentionClient};
+
+    use super::*;
+
+    /// Verifies one mention, leaving any others in the queue alone
+    async fn verify_mention(pool: &Pool<Postgres>, id: i32) {
+        let mention = sqlx::query_as::<_, Webmention>("SELECT * FROM webmention WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        verify(pool, &WebmentionClient::allowing_private(), &mention).await;
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_webmention() {
+        let pool = get_db().await;
+        let blog_id = 4401;
+        test_blog(blog_id).create_blog(&pool).await.unwrap();
+        let target = format!("https://www.danielsteman.com/blog/{}", blog_id);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let page = format!("<title>Linking post</title><a href=\"{}\">post</a>", target);
+        let app = Router::new()
+            .route("/links", get(move || async move { Html(page) }))
+            .route(
+                "/unrelated",
+                get(|| async { Html("<title>Nothing</title>") }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let linking = format!("http://{}/links", address);
+        let unrelated = format!("http://{}/unrelated", address);
+        let linking_id = Webmention::enqueue(&pool, &linking, &target, blog_id)
+            .await
+            .unwrap();
+        let unrelated_id = Webmention::enqueue(&pool, &unrelated, &target, blog_id)
+            .await
+            .unwrap();
+
+        verify_mention(&pool, linking_id).await;
+        verify_mention(&pool, unrelated_id).await;
+
+        let status = |id: i32| {
+            sqlx::query_scalar::<_, String>("SELECT status FROM webmention WHERE id = $1")
+                .bind(id)
+                .fetch_one(&pool)
+        };
+        assert_eq!(status(linking_id).await.unwrap(), "verified");
+        assert_eq!(status(unrelated_id).await.unwrap(), "rejected");
+
+        // Only shown once approved
+        let shown = |mentions: Vec<Webmention>| mentions.iter().any(|m| m.id == linking_id);
+        assert!(!shown(
+            Webmention::get_approved(&pool, blog_id).await.unwrap()
+        ));
+        sqlx::query("UPDATE webmention SET approved = TRUE WHERE id = $1")
+            .bind(linking_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mentions = Webmention::get_approved(&pool, blog_id).await.unwrap();
+        let mention = mentions.iter().find(|m| m.id == linking_id).unwrap();
+        assert_eq!(mention.display_name(), "Linking post");
+
+        // Sending it again needs another approval
+        Webmention::enqueue(&pool, &linking, &target, blog_id)
+            .await
+            .unwrap();
+        verify_mention(&pool, linking_id).await;
+        assert_eq!(status(linking_id).await.unwrap(), "verified");
+        assert!(!shown(
+            Webmention::get_approved(&pool, blog_id).await.unwrap()
+        ));
+
+        // Its mentions go with it
+        sqlx::query("DELETE FROM blog WHERE id = $1")
+            .bind(blog_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
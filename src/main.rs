@@ -2,15 +2,18 @@ mod crud;
 mod routes;
 mod utils;
 
-use std::env::{self, set_var};
+use std::env::set_var;
+use std::sync::Arc;
 
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
 use include_dir::include_dir;
 use lambda_http::{run, Error};
 use tower_http::services::ServeDir;
+use utils::config::{AppState, SiteConfig};
 use utils::db::get_db;
 use utils::ingest;
 
@@ -37,32 +40,79 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let pool = get_db().await;
+    let site = Arc::new(SiteConfig::load());
+    let state = AppState {
+        pool: pool.clone(),
+        site: site.clone(),
+        mailer: utils::mailer::mailer_from_env(&site),
+        unsubscribe: Arc::new(utils::token::UnsubscribeKey::from_env()),
+    };
 
-    if let Ok(env) = env::var("ENV") {
-        if env == "PROD" {
-            sqlx::migrate!()
-                .set_locking(false)
-                .run(&pool)
-                .await
-                .expect("Failed to perform database migrations");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return utils::tasks::command(&state, &args)
+            .await
+            .map_err(Error::from);
+    }
+    if std::env::var("SCHEDULED_TASKS").is_ok_and(|tasks| tasks == "true") {
+        return utils::tasks::run_scheduled(state).await;
+    }
 
-            ingest::ingest_articles().await;
-        }
+    if utils::robots::is_production() {
+        sqlx::migrate!()
+            .set_locking(false)
+            .run(&pool)
+            .await
+            .expect("Failed to perform database migrations");
+
+        ingest::ingest_articles().await;
     }
 
-    let app = Router::new()
+    let mut app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(routes::root::root))
         .route("/robots.txt", get(routes::robots::robots_txt))
+        .route("/feed.xml", get(routes::feed::rss))
+        .route("/atom.xml", get(routes::feed::atom))
+        .route("/feed.json", get(routes::feed::json_feed))
+        .route("/tags/:tag", get(routes::tags::tag))
+        .route("/tags/:tag/feed.xml", get(routes::feed::tag_rss))
         .route("/sitemap.xml", get(routes::sitemap::sitemap_xml))
+        .route("/sitemaps/:file", get(routes::sitemap::sitemap_page))
         .route("/blogs", get(routes::blogs::blogs))
+        .route("/api/blogs", get(routes::api::blogs))
+        .route("/archive", get(routes::archive::archive))
+        .route("/archive/:year", get(routes::archive::archive_year))
+        .route("/archive/:year/:month", get(routes::archive::archive_month))
         .route("/blog/:id", get(routes::blog::blog))
+        .route("/blog/:id/og.png", get(routes::og::og_image))
         .route("/resume", get(routes::resume::resume))
         .route("/radar", get(routes::radar::radar))
         .route("/search", post(routes::search::search))
-        .route("/health/", get(routes::health::health_check))
+        .route("/webmention", post(routes::webmention::webmention))
+        .route("/subscribe", post(routes::subscribe::subscribe))
+        .route("/subscribe/confirm/:token", get(routes::subscribe::confirm))
+        .route(
+            "/unsubscribe/:token",
+            get(routes::unsubscribe::unsubscribe_page).post(routes::unsubscribe::unsubscribe),
+        )
+        .route("/search/suggest", get(routes::suggest::suggest))
+        .route("/health", get(routes::health::health_check))
         .fallback(routes::handler_404::handler_404)
-        .with_state(pool);
+        .with_state(state);
+
+    if let Some(key) = utils::indexnow::indexnow_key().filter(|_| site.features.indexnow) {
+        app = app.route(&format!("/{}.txt", key), get(routes::indexnow::key_file));
+    }
+
+    app = app.layer(middleware::from_fn_with_state(
+        site.clone(),
+        utils::canonical::canonicalize,
+    ));
+
+    if !utils::robots::is_production() {
+        app = app.layer(middleware::map_response(utils::robots::noindex));
+    }
 
     run(app).await
 }
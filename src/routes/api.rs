@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use time::macros::format_description;
+
+use crate::crud::blog::Blog;
+use crate::routes::blogs::Ordering;
+use crate::utils::config::SiteConfig;
+use crate::utils::pagination::{PageRequest, PaginationParams};
+
+#[derive(Serialize, Debug)]
+struct BlogSummary {
+    id: i32,
+    title: String,
+    summary: String,
+    date: String,
+    tags: Vec<String>,
+    pinned: bool,
+    url: String,
+}
+
+impl BlogSummary {
+    fn new(blog: Blog, site: &SiteConfig) -> BlogSummary {
+        let date_format = format_description!("[year]-[month]-[day]");
+        BlogSummary {
+            url: site.post_url(blog.id),
+            date: blog.date.format(&date_format).unwrap_or_default(),
+            id: blog.id,
+            title: blog.title,
+            summary: blog.summary,
+            tags: blog.tags,
+            pinned: blog.pinned,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct BlogList {
+    items: Vec<BlogSummary>,
+    next_cursor: Option<String>,
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+pub async fn blogs(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Query(params): Query<PaginationParams>,
+    Query(ordering): Query<Ordering>,
+) -> Response {
+    if params.has_page() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            String::from("page isn't supported, follow next_cursor instead"),
+        );
+    }
+    let cursor = match params.cursor() {
+        Ok(cursor) => cursor,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    let request = match PageRequest::try_from(params) {
+        Ok(request) => request,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    if !ordering.order.supports_cursor() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            String::from("cursor pagination requires newest or oldest order"),
+        );
+    }
+
+    match Blog::get_blogs_after(&pool, cursor, request.per_page, ordering.order).await {
+        Ok(page) => Json(BlogList {
+            items: page
+                .items
+                .into_iter()
+                .map(|blog| BlogSummary::new(blog, &site))
+                .collect(),
+            next_cursor: page.next_cursor,
+        })
+        .into_response(),
+        Err(err) => {
+            eprintln!("Error fetching blogs: {}", err);
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                String::from("failed to fetch blogs"),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::config::test_site;
+    use crate::utils::db::get_db;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blogs() {
+        let pool = get_db().await;
+        let site = Arc::new(test_site());
+        let query = |query: &str| {
+            let uri: axum::http::Uri = format!("/api/blogs?{}", query).parse().unwrap();
+            blogs(
+                State(pool.clone()),
+                State(site.clone()),
+                Query::try_from_uri(&uri).unwrap(),
+                Query::try_from_uri(&uri).unwrap(),
+            )
+        };
+
+        assert_eq!(query("page=2").await.status(), StatusCode::BAD_REQUEST);
+
+        let response = query("per_page=1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let list: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for item in list["items"].as_array().unwrap() {
+            let url = item["url"].as_str().unwrap();
+            assert_eq!(url, format!("https://www.example.com/blog/{}", item["id"]));
+        }
+    }
+}
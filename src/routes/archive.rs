@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::types::time::Date;
+use sqlx::PgPool;
+use time::Month;
+
+use crate::crud::blog::{ArchiveCount, ArchiveEntry, Blog};
+use crate::utils::config::SiteConfig;
+use crate::utils::html::HtmlTemplate;
+
+#[derive(Template)]
+#[template(path = "archive.html")]
+struct ArchiveTemplate {
+    site: Arc<SiteConfig>,
+    heading: String,
+    // Link back up the timeline, absent on the full archive
+    parent: Option<(String, String)>,
+    years: Vec<ArchiveYear>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArchiveYear {
+    pub year: i32,
+    pub count: i64,
+    pub months: Vec<ArchiveMonth>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArchiveMonth {
+    pub month: Month,
+    pub number: u8,
+    pub count: i64,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+pub async fn archive(State(pool): State<PgPool>, State(site): State<Arc<SiteConfig>>) -> Response {
+    render(&pool, site, None, String::from("Archive"), None).await
+}
+
+pub async fn archive_year(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(year): Path<i32>,
+) -> Response {
+    let to = year
+        .checked_add(1)
+        .and_then(|next| Date::from_calendar_date(next, Month::January, 1).ok());
+    let range = match (Date::from_calendar_date(year, Month::January, 1).ok(), to) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return (StatusCode::NOT_FOUND, "nothing to see here").into_response(),
+    };
+    let parent = (String::from("/archive"), String::from("Archive"));
+    render(&pool, site, Some(range), year.to_string(), Some(parent)).await
+}
+
+pub async fn archive_month(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path((year, month)): Path<(i32, u8)>,
+) -> Response {
+    let Ok(month) = Month::try_from(month) else {
+        return (StatusCode::NOT_FOUND, "nothing to see here").into_response();
+    };
+    let next_year = if month == Month::December {
+        year.checked_add(1)
+    } else {
+        Some(year)
+    };
+    let to = next_year.and_then(|next| Date::from_calendar_date(next, month.next(), 1).ok());
+    let range = match (Date::from_calendar_date(year, month, 1).ok(), to) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return (StatusCode::NOT_FOUND, "nothing to see here").into_response(),
+    };
+    let parent = (format!("/archive/{}", year), year.to_string());
+    render(
+        &pool,
+        site,
+        Some(range),
+        format!("{} {}", month, year),
+        Some(parent),
+    )
+    .await
+}
+
+async fn render(
+    pool: &PgPool,
+    site: Arc<SiteConfig>,
+    range: Option<(Date, Date)>,
+    heading: String,
+    parent: Option<(String, String)>,
+) -> Response {
+    let archive = tokio::try_join!(
+        Blog::get_archive_counts(pool, range),
+        Blog::get_archive(pool, range)
+    );
+    let years = match archive {
+        Ok((counts, entries)) => timeline(counts, entries),
+        Err(err) => {
+            eprintln!("Error fetching archive: {}", err);
+            vec![]
+        }
+    };
+
+    let status = if years.is_empty() && range.is_some() {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::OK
+    };
+    let template = ArchiveTemplate {
+        site,
+        heading,
+        parent,
+        years,
+    };
+    (status, HtmlTemplate(template)).into_response()
+}
+
+/// Groups posts by year and month. Both inputs are expected newest first.
+pub fn timeline(counts: Vec<ArchiveCount>, entries: Vec<ArchiveEntry>) -> Vec<ArchiveYear> {
+    let mut entries = entries.into_iter().peekable();
+    let mut years: Vec<ArchiveYear> = Vec::new();
+
+    for count in counts {
+        let Ok(month) = Month::try_from(count.month as u8) else {
+            continue;
+        };
+        let mut month_entries = Vec::new();
+        while let Some(entry) =
+            entries.next_if(|entry| entry.date.year() == count.year && entry.date.month() == month)
+        {
+            month_entries.push(entry);
+        }
+
+        let archive_month = ArchiveMonth {
+            month,
+            number: month as u8,
+            count: count.count,
+            entries: month_entries,
+        };
+        match years.last_mut() {
+            Some(year) if year.year == count.year => {
+                year.count += count.count;
+                year.months.push(archive_month);
+            }
+            _ => years.push(ArchiveYear {
+                year: count.year,
+                count: count.count,
+                months: vec![archive_month],
+            }),
+        }
+    }
+
+    years
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::config::test_site;
+    use crate::utils::db::unreachable_db;
+
+    use super::*;
+
+    fn entry(id: i32, year: i32, month: Month) -> ArchiveEntry {
+        ArchiveEntry {
+            id,
+            title: format!("Post {}", id),
+            date: Date::from_calendar_date(year, month, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_timeline() {
+        let counts = vec![
+            ArchiveCount {
+                year: 2024,
+                month: 3,
+                count: 2,
+            },
+            ArchiveCount {
+                year: 2024,
+                month: 1,
+                count: 1,
+            },
+            ArchiveCount {
+                year: 2023,
+                month: 12,
+                count: 1,
+            },
+        ];
+        let entries = vec![
+            entry(4, 2024, Month::March),
+            entry(3, 2024, Month::March),
+            entry(2, 2024, Month::January),
+            entry(1, 2023, Month::December),
+        ];
+
+        let years = timeline(counts, entries);
+
+        assert_eq!(years.len(), 2);
+        assert_eq!(years[0].count, 3);
+        assert_eq!(years[0].months[0].number, 3);
+        assert_eq!(years[0].months[0].entries.len(), 2);
+        assert_eq!(years[1].months[0].month, Month::December);
+        assert_eq!(years[1].months[0].entries[0].id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_archive_out_of_range() {
+        let pool = unreachable_db();
+        let site = Arc::new(test_site());
+
+        let response = archive_year(State(pool.clone()), State(site.clone()), Path(i32::MAX)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = archive_month(State(pool), State(site), Path((i32::MAX, 12))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
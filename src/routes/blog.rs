@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use time::macros::format_description;
 use time::Month;
 
@@ -8,7 +10,12 @@ use axum::{
 
 use sqlx::PgPool;
 
-use crate::{crud::blog::Blog, utils::html::HtmlTemplate};
+use crate::crud::blog::Blog;
+use crate::crud::webmention::Webmention;
+use crate::utils::config::SiteConfig;
+use crate::utils::feed::rfc3339;
+use crate::utils::html::HtmlTemplate;
+use crate::utils::jsonld::{to_script, BlogPosting, BreadcrumbList};
 
 use askama::Template;
 use sqlx::types::time::Date;
@@ -16,64 +23,89 @@ use sqlx::types::time::Date;
 #[derive(Template)]
 #[template(path = "blog.html")]
 struct BlogTemplate {
+    site: Arc<SiteConfig>,
     id: i32,
     title: String,
     summary: String,
     body: String,
     date: Date,
-    date_iso: String,
     keywords: String,
-    tags_keywords: String,
-    has_tags: bool,
+    json_ld: Vec<String>,
+    related: Vec<Blog>,
+    mentions: Vec<Webmention>,
 }
 
-pub async fn blog(State(pool): State<PgPool>, Path(id): Path<i32>) -> impl IntoResponse {
+pub async fn blog(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
     match Blog::get_blog(&pool, id).await {
         Ok(blog) => {
             // Format date as ISO 8601 for structured data
             let date_format = format_description!("[year]-[month]-[day]");
             let date_iso = blog.date.format(&date_format).unwrap_or_default();
 
-            // Create keywords string from tags
-            let keywords = if blog.tags.is_empty() {
-                "Daniel Steman, software engineering, tech blog".to_string()
+            let posting = BlogPosting::new(
+                &site,
+                id,
+                &blog.title,
+                &blog.summary,
+                date_iso,
+                rfc3339(blog.updated_at),
+                &blog.tags,
+            );
+            let breadcrumbs = BreadcrumbList::new(vec![
+                (String::from("Home"), site.url("/")),
+                (String::from("Blog"), site.url("/blogs")),
+                (blog.title.clone(), posting.url.clone()),
+            ]);
+
+            let related = Blog::get_related_blogs(&pool, id)
+                .await
+                .unwrap_or_else(|err| {
+                    eprintln!("Error fetching related blogs: {}", err);
+                    vec![]
+                });
+
+            let mentions = if site.features.webmentions {
+                Webmention::get_approved(&pool, id)
+                    .await
+                    .unwrap_or_else(|err| {
+                        eprintln!("Error fetching webmentions: {}", err);
+                        vec![]
+                    })
             } else {
-                format!(
-                    "{}, Daniel Steman, software engineering, tech blog",
-                    blog.tags.join(", ")
-                )
+                vec![]
             };
 
-            // Create tags keywords for structured data
-            let tags_keywords = blog.tags.join(", ");
-
             HtmlTemplate(BlogTemplate {
+                keywords: site.keywords_with(&blog.tags),
+                site,
                 id,
                 title: blog.title.clone(),
                 summary: blog.summary.clone(),
                 body: blog.body.clone(),
                 date: blog.date,
-                date_iso,
-                keywords,
-                tags_keywords,
-                has_tags: !blog.tags.is_empty(),
+                json_ld: vec![to_script(&posting), to_script(&breadcrumbs)],
+                related,
+                mentions,
             })
         }
         Err(_) => {
             let error_date = Date::from_calendar_date(1995, Month::April, 13).unwrap();
-            let date_format = format_description!("[year]-[month]-[day]");
-            let date_iso = error_date.format(&date_format).unwrap_or_default();
 
             HtmlTemplate(BlogTemplate {
+                keywords: site.keywords_with(&[]),
+                site,
                 id: 0,
                 title: "Not Found".to_string(),
                 summary: "Blog post not found".to_string(),
                 body: "This blog hasn't been written yet...".to_string(),
                 date: error_date,
-                date_iso,
-                keywords: "Daniel Steman, software engineering, tech blog".to_string(),
-                tags_keywords: String::new(),
-                has_tags: false,
+                json_ld: vec![],
+                related: vec![],
+                mentions: vec![],
             })
         }
     }
@@ -1,63 +1,230 @@
+use std::sync::Arc;
+
 use askama::Template;
 use axum::extract::Query;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
 use axum::{extract::State, response::IntoResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
 
-use crate::crud::blog::Blog;
+use crate::crud::blog::{Blog, ListOrder};
+use crate::routes::search::SearchFacets;
+use crate::utils::config::SiteConfig;
 use crate::utils::html::HtmlTemplate;
+use crate::utils::jsonld::{to_script, ItemList};
+use crate::utils::pagination::{Page, PageRequest, PaginationParams};
 
 #[derive(Template)]
 #[template(path = "blogs.html")]
 struct BlogsTemplate {
+    site: Arc<SiteConfig>,
+    blogs: Vec<Blog>,
+    // Numbered pages, absent when paging by cursor
+    pagination: Option<Page<()>>,
+    next_cursor: Option<String>,
+    per_page: i64,
+    order: ListOrder,
+    facets: Option<SearchFacets>,
+    // Query string of the next batch for the infinite scroll sentinel
+    next_query: Option<String>,
+    // ItemList markup of the listed posts
+    json_ld: String,
+}
+
+/// Only the next batch of cards and an out of band pager, for htmx requests.
+#[derive(Template)]
+#[template(path = "blogs-partial.html")]
+struct BlogsPartialTemplate {
     blogs: Vec<Blog>,
-    pagination: Pagination,
+    pagination: Option<Page<()>>,
+    next_cursor: Option<String>,
+    per_page: i64,
+    order: ListOrder,
+    facets: Option<SearchFacets>,
+    next_query: Option<String>,
+}
+
+impl BlogsTemplate {
+    fn new(
+        site: Arc<SiteConfig>,
+        blogs: Vec<Blog>,
+        pagination: Option<Page<()>>,
+        next_cursor: Option<String>,
+        per_page: i64,
+        order: ListOrder,
+    ) -> BlogsTemplate {
+        // Prefer the cursor so batches stay stable when a post is published
+        let next_query = match (&next_cursor, pagination.as_ref().and_then(Page::next_page)) {
+            (Some(cursor), _) => Some(format!("cursor={}", cursor)),
+            (None, Some(page)) => Some(format!("page={}", page)),
+            _ => None,
+        }
+        .map(|query| format!("{}&per_page={}&order={}", query, per_page, order.as_str()));
+
+        let items = ItemList::new(
+            blogs
+                .iter()
+                .map(|blog| (blog.title.clone(), site.post_url(blog.id)))
+                .collect(),
+        );
+
+        BlogsTemplate {
+            json_ld: to_script(&items),
+            site,
+            blogs,
+            pagination,
+            next_cursor,
+            per_page,
+            order,
+            facets: None,
+            next_query,
+        }
+    }
+}
+
+impl From<BlogsTemplate> for BlogsPartialTemplate {
+    fn from(template: BlogsTemplate) -> Self {
+        BlogsPartialTemplate {
+            blogs: template.blogs,
+            pagination: template.pagination,
+            next_cursor: template.next_cursor,
+            per_page: template.per_page,
+            order: template.order,
+            facets: template.facets,
+            next_query: template.next_query,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
-pub struct Pagination {
-    page: usize,
-    per_page: usize,
+pub struct Ordering {
+    #[serde(default)]
+    pub order: ListOrder,
 }
 
 pub async fn blogs(
     State(pool): State<PgPool>,
-    pagination: Option<Query<Pagination>>,
-) -> impl IntoResponse {
-    let pagination = match pagination {
-        Some(pagination) => Pagination { ..*pagination },
-        None => Pagination {
-            page: 1,
-            per_page: 20,
-        },
+    State(site): State<Arc<SiteConfig>>,
+    Query(params): Query<PaginationParams>,
+    Query(ordering): Query<Ordering>,
+    headers: HeaderMap,
+) -> Response {
+    let cursor = match params.cursor() {
+        Ok(cursor) => cursor,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
     };
+    let request = match PageRequest::try_from(params) {
+        Ok(request) => request,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+    let order = ordering.order;
+
+    let template = if cursor.is_some() {
+        if !order.supports_cursor() {
+            return (
+                StatusCode::BAD_REQUEST,
+                "cursor pagination requires newest or oldest order",
+            )
+                .into_response();
+        }
 
-    let limit = pagination.per_page;
-    let offset = (pagination.page - 1) * limit;
-
-    match Blog::get_blogs(&pool, limit, offset).await {
-        Ok(blogs) => {
-            let pagination_data = Pagination {
-                page: pagination.page,
-                per_page: pagination.per_page,
-            };
-            let template = BlogsTemplate {
-                blogs,
-                pagination: pagination_data,
-            };
-            HtmlTemplate(template)
+        match Blog::get_blogs_after(&pool, cursor, request.per_page, order).await {
+            Ok(page) => BlogsTemplate::new(
+                site,
+                page.items,
+                None,
+                page.next_cursor,
+                page.per_page,
+                order,
+            ),
+            Err(err) => {
+                eprintln!("Error fetching blogs: {}", err);
+                error_template(site, request, order)
+            }
         }
-        Err(err) => {
-            eprintln!("Error fetching blogs: {}", err);
-
-            let error_template = BlogsTemplate {
-                blogs: vec![],
-                pagination: Pagination {
-                    page: 0,
-                    per_page: 0,
-                },
-            };
-            HtmlTemplate(error_template)
+    } else {
+        match Blog::get_blogs(&pool, request, order).await {
+            Ok(page) => {
+                let (blogs, pagination) = page.into_parts();
+                let next_cursor = match (pagination.next_page(), order.supports_cursor()) {
+                    (Some(_), true) => blogs.last().map(|blog| blog.cursor().encode()),
+                    _ => None,
+                };
+                BlogsTemplate::new(
+                    site,
+                    blogs,
+                    Some(pagination),
+                    next_cursor,
+                    request.per_page,
+                    order,
+                )
+            }
+            Err(err) => {
+                eprintln!("Error fetching blogs: {}", err);
+                error_template(site, request, order)
+            }
         }
+    };
+
+    let vary = [(header::VARY, "HX-Request")];
+    if headers.contains_key("HX-Request") {
+        (vary, HtmlTemplate(BlogsPartialTemplate::from(template))).into_response()
+    } else {
+        (vary, HtmlTemplate(template)).into_response()
+    }
+}
+
+fn error_template(site: Arc<SiteConfig>, request: PageRequest, order: ListOrder) -> BlogsTemplate {
+    BlogsTemplate::new(
+        site,
+        vec![],
+        Some(Page::empty(request)),
+        None,
+        request.per_page,
+        order,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::config::test_site;
+
+    use super::*;
+
+    #[test]
+    fn test_next_query() {
+        let site = Arc::new(test_site());
+        let request = PageRequest::new(1, 10).unwrap();
+        let pagination = || Some(Page::<()>::new(vec![], request, 25));
+
+        let by_cursor = BlogsTemplate::new(
+            site.clone(),
+            vec![],
+            pagination(),
+            Some(String::from("abc")),
+            10,
+            ListOrder::Newest,
+        );
+        assert_eq!(
+            by_cursor.next_query.as_deref(),
+            Some("cursor=abc&per_page=10&order=newest")
+        );
+
+        let by_page = BlogsTemplate::new(
+            site.clone(),
+            vec![],
+            pagination(),
+            None,
+            10,
+            ListOrder::Title,
+        );
+        assert_eq!(
+            by_page.next_query.as_deref(),
+            Some("page=2&per_page=10&order=title")
+        );
+
+        let last = BlogsTemplate::new(site, vec![], None, None, 10, ListOrder::Newest);
+        assert_eq!(last.next_query, None);
     }
 }
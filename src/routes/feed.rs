@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+use crate::utils::feed::{last_updated, rfc3339, tag_feed_url, FeedEntry, JsonFeed, FEED_POSTS};
+use crate::utils::http::{conditional_response, http_date};
+
+#[derive(Template)]
+#[template(path = "feed.xml")]
+struct RssTemplate<'a> {
+    title: String,
+    description: &'a str,
+    site_url: &'a str,
+    feed_url: String,
+    last_build_date: String,
+    entries: Vec<FeedEntry>,
+}
+
+#[derive(Template)]
+#[template(path = "atom.xml")]
+struct AtomTemplate<'a> {
+    title: &'a str,
+    author: &'a str,
+    site_url: &'a str,
+    updated: String,
+    entries: Vec<FeedEntry>,
+}
+
+async fn get_entries(
+    pool: &PgPool,
+    site: &SiteConfig,
+    tag: Option<&str>,
+) -> Result<Vec<FeedEntry>, Response> {
+    match Blog::get_published_blogs(pool, tag, Some(FEED_POSTS)).await {
+        Ok(blogs) => Ok(blogs
+            .into_iter()
+            .map(|blog| FeedEntry::from_blog(blog, site))
+            .collect()),
+        Err(err) => {
+            eprintln!("Error fetching blogs for feed: {}", err);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+fn render(
+    headers: &HeaderMap,
+    content_type: &'static str,
+    template: impl Template,
+    last_modified: Option<OffsetDateTime>,
+) -> Response {
+    match template.render() {
+        Ok(body) => conditional_response(headers, content_type, body, last_modified),
+        Err(err) => {
+            eprintln!("Failed to render feed: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn rss(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    headers: HeaderMap,
+) -> Response {
+    let entries = match get_entries(&pool, &site, None).await {
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+    let updated = last_updated(&entries);
+    let template = RssTemplate {
+        title: site.name.clone(),
+        description: &site.description,
+        site_url: &site.base_url,
+        feed_url: site.url("/feed.xml"),
+        last_build_date: updated.map(http_date).unwrap_or_default(),
+        entries,
+    };
+    render(
+        &headers,
+        "application/rss+xml; charset=utf-8",
+        template,
+        updated,
+    )
+}
+
+pub async fn tag_rss(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(tag): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let entries = match get_entries(&pool, &site, Some(&tag)).await {
+        Ok(entries) if entries.is_empty() => {
+            return (StatusCode::NOT_FOUND, "nothing to see here").into_response()
+        }
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+    let updated = last_updated(&entries);
+    let template = RssTemplate {
+        title: format!("{}: {}", site.name, tag),
+        description: &site.description,
+        site_url: &site.base_url,
+        feed_url: tag_feed_url(&site, &tag),
+        last_build_date: updated.map(http_date).unwrap_or_default(),
+        entries,
+    };
+    render(
+        &headers,
+        "application/rss+xml; charset=utf-8",
+        template,
+        updated,
+    )
+}
+
+pub async fn json_feed(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    headers: HeaderMap,
+) -> Response {
+    let entries = match get_entries(&pool, &site, None).await {
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+    let updated = last_updated(&entries);
+    match serde_json::to_string(&JsonFeed::new(&site, entries)) {
+        Ok(body) => conditional_response(
+            &headers,
+            "application/feed+json; charset=utf-8",
+            body,
+            updated,
+        ),
+        Err(err) => {
+            eprintln!("Failed to serialize feed: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn atom(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    headers: HeaderMap,
+) -> Response {
+    let entries = match get_entries(&pool, &site, None).await {
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+    let updated = last_updated(&entries);
+    let template = AtomTemplate {
+        title: &site.name,
+        author: &site.author,
+        site_url: &site.base_url,
+        updated: rfc3339(updated.unwrap_or(OffsetDateTime::UNIX_EPOCH)),
+        entries,
+    };
+    render(
+        &headers,
+        "application/atom+xml; charset=utf-8",
+        template,
+        updated,
+    )
+}
@@ -0,0 +1,24 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::utils::indexnow::indexnow_key;
+
+/// Serves the IndexNow key at `/{key}.txt`, so search engines can verify
+/// submissions came from this site.
+pub async fn key_file() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "text/plain; charset=utf-8".parse().unwrap(),
+    );
+    match indexnow_key() {
+        Some(key) => (StatusCode::OK, headers, key),
+        None => (
+            StatusCode::NOT_FOUND,
+            headers,
+            String::from("nothing to see here"),
+        ),
+    }
+}
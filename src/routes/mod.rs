@@ -1,10 +1,20 @@
+pub mod api;
+pub mod archive;
 pub mod blog;
 pub mod blogs;
+pub mod feed;
 pub mod handler_404;
 pub mod health;
+pub mod indexnow;
+pub mod og;
 pub mod radar;
 pub mod resume;
 pub mod robots;
 pub mod root;
 pub mod search;
 pub mod sitemap;
+pub mod subscribe;
+pub mod suggest;
+pub mod tags;
+pub mod unsubscribe;
+pub mod webmention;
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+use crate::utils::http::conditional_response;
+use crate::utils::og::render_og_image;
+
+// Rendered cards by post id, with the `updated_at` they were rendered from
+type OgCache = Mutex<HashMap<i32, (OffsetDateTime, Bytes)>>;
+
+static OG_CACHE: OnceLock<OgCache> = OnceLock::new();
+
+fn cached(id: i32, updated_at: OffsetDateTime) -> Option<Bytes> {
+    let cache = OG_CACHE.get_or_init(Default::default).lock().unwrap();
+    match cache.get(&id) {
+        Some((rendered_at, png)) if *rendered_at == updated_at => Some(png.clone()),
+        _ => None,
+    }
+}
+
+pub async fn og_image(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Response {
+    let blog = match Blog::get_blog(&pool, id).await {
+        Ok(blog) => blog,
+        Err(_) => return (StatusCode::NOT_FOUND, "nothing to see here").into_response(),
+    };
+    let updated_at = blog.updated_at;
+
+    let png = match cached(id, updated_at) {
+        Some(png) => png,
+        None => match tokio::task::spawn_blocking(move || render_og_image(&blog, &site)).await {
+            Ok(Ok(png)) => {
+                let png = Bytes::from(png);
+                OG_CACHE
+                    .get_or_init(Default::default)
+                    .lock()
+                    .unwrap()
+                    .insert(id, (updated_at, png.clone()));
+                png
+            }
+            Ok(Err(err)) => {
+                eprintln!("Error rendering og image for blog {}: {}", id, err);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+            Err(err) => {
+                eprintln!("Error rendering og image for blog {}: {}", id, err);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        },
+    };
+
+    let mut response = conditional_response(&headers, "image/png", png, Some(updated_at));
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=86400"),
+    );
+    response
+}
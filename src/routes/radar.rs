@@ -1,13 +1,18 @@
+use std::sync::Arc;
+
 use askama::Template;
-use axum::response::IntoResponse;
+use axum::{extract::State, response::IntoResponse};
 
+use crate::utils::config::SiteConfig;
 use crate::utils::html::HtmlTemplate;
 
 #[derive(Template)]
 #[template(path = "radar.html")]
-struct RadarTemplate {}
+struct RadarTemplate {
+    site: Arc<SiteConfig>,
+}
 
-pub async fn radar() -> impl IntoResponse {
-    let template = RadarTemplate {};
+pub async fn radar(State(site): State<Arc<SiteConfig>>) -> impl IntoResponse {
+    let template = RadarTemplate { site };
     HtmlTemplate(template)
 }
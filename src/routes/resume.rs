@@ -1,117 +1,43 @@
+use std::sync::Arc;
+
 use askama::Template;
-use axum::response::IntoResponse;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use axum::{extract::State, response::IntoResponse};
 
+use crate::utils::config::{Resume, SiteConfig};
 use crate::utils::html::HtmlTemplate;
+use crate::utils::jsonld::{to_script, Organization, Person};
 
 #[derive(Template)]
 #[template(path = "resume.html")]
 struct ResumeTemplate {
+    site: Arc<SiteConfig>,
     resume_data: Resume,
+    json_ld: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Period {
-    from: String,
-    to: String,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct School {
-    name: String,
-    url: String,
+/// The author's `Person` markup, with their current employer and schools.
+fn person(site: &SiteConfig) -> Person {
+    let mut person = Person::profile(site);
+    person.works_for = site
+        .resume
+        .experience
+        .iter()
+        .find(|experience| experience.period.to == "Present")
+        .map(|experience| Organization::new(&experience.employer, None));
+    person.alumni_of = site
+        .resume
+        .education
+        .iter()
+        .map(|study| Organization::school(&study.school.name, &study.school.url))
+        .collect();
+    person
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Experience {
-    employer: String,
-    title: String,
-    period: Period,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct Study {
-    school: School,
-    title: String,
-    period: Period,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct Resume {
-    experience: Vec<Experience>,
-    education: Vec<Study>,
-}
-
-pub async fn resume() -> impl IntoResponse {
-    let resume_data = json!({
-        "experience": [
-            {
-                "employer": "Bridgefund",
-                "title": "Soft Engineer - Data Platform",
-                "period": {
-                    "from": "October 2023",
-                    "to": "Present"
-                }
-            },
-            {
-                "employer": "a.s.r.",
-                "title": "Software engineer",
-                "period": {
-                    "from": "January 2021",
-                    "to": "September 2023"
-                }
-            },
-            {
-                "employer": "Virtuagym",
-                "title": "Analyst",
-                "period": {
-                    "from": "August 2017",
-                    "to": "December 2020"
-                }
-            }
-        ],
-        "education": [
-            {
-                "school": {
-                    "name": "MOOC",
-                    "url": "https://fullstackopen.com/en/"
-                },
-                "title": "Full Stack Open",
-                "period": {
-                    "from": "September 2020",
-                    "to": "December 2020"
-                }
-            },
-            {
-                "school": {
-                    "name": "Vrije Universiteit Amsterdam",
-                    "url": "https://vu.nl/en/education/master/finance-duisenberg-honours-programme-in-finance-and-technology"
-                },
-                "title": "MSc Finance & Technology (honours programme)",
-                "period": {
-                    "from": "February 2019",
-                    "to": "August 2020"
-                }
-            },
-            {
-                "school": {
-                    "name": "The Hague University of Applied Sciences",
-                    "url": "https://www.dehaagsehogeschool.nl/opleidingen/hbo-bachelor/international-business-4-jaar"
-                },
-                "title": "BSc International Business",
-                "period": {
-                    "from": "September 2013",
-                    "to": "August 2017"
-                }
-            }
-        ]
-    });
-
-    let deser_resume_data: Resume = serde_json::from_value(resume_data).unwrap();
-
+pub async fn resume(State(site): State<Arc<SiteConfig>>) -> impl IntoResponse {
     let template = ResumeTemplate {
-        resume_data: deser_resume_data,
+        json_ld: to_script(&person(&site)),
+        resume_data: site.resume.clone(),
+        site,
     };
     HtmlTemplate(template)
 }
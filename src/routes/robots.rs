@@ -1,10 +1,16 @@
+use std::sync::Arc;
+
 use axum::{
+    extract::State,
     http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
 };
 
-pub async fn robots_txt() -> impl IntoResponse {
-    let content = include_str!("../../robots.txt");
+use crate::utils::config::SiteConfig;
+use crate::utils::robots::{is_production, robots_rules};
+
+pub async fn robots_txt(State(site): State<Arc<SiteConfig>>) -> impl IntoResponse {
+    let content = robots_rules(is_production(), &site.base_url);
     let mut headers = HeaderMap::new();
     headers.insert(
         header::CONTENT_TYPE,
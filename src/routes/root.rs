@@ -1,13 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use askama::Template;
-use axum::response::IntoResponse;
+use axum::{extract::State, response::IntoResponse};
+use sqlx::{Error, PgPool};
 
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
 use crate::utils::html::HtmlTemplate;
+use crate::utils::jsonld::{to_script, Person, WebPage, WebSite};
+
+const LATEST_POSTS: i64 = 5;
+// Show the static landing page rather than wait on an unreachable database
+const DB_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Template)]
 #[template(path = "index.html")]
-struct RootTemplate {}
+struct RootTemplate {
+    site: Arc<SiteConfig>,
+    featured: Vec<Blog>,
+    latest: Vec<Blog>,
+    json_ld: Vec<String>,
+}
+
+async fn get_posts(pool: &PgPool) -> Result<(Vec<Blog>, Vec<Blog>), Error> {
+    tokio::try_join!(
+        Blog::get_featured_blogs(pool),
+        Blog::get_latest_blogs(pool, LATEST_POSTS)
+    )
+}
+
+pub async fn root(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+) -> impl IntoResponse {
+    let (featured, latest) = match tokio::time::timeout(DB_TIMEOUT, get_posts(&pool)).await {
+        Ok(Ok(posts)) => posts,
+        Ok(Err(err)) => {
+            eprintln!("Error fetching landing page posts: {}", err);
+            (vec![], vec![])
+        }
+        Err(_) => {
+            eprintln!("Timed out fetching landing page posts");
+            (vec![], vec![])
+        }
+    };
+
+    let page = WebPage::new(&site, &site.title, &site.description, &site.url("/"));
+    HtmlTemplate(RootTemplate {
+        json_ld: vec![
+            to_script(&Person::profile(&site)),
+            to_script(&WebSite::new(&site)),
+            to_script(&page),
+        ],
+        site,
+        featured,
+        latest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::config::test_site;
+    use crate::utils::db::unreachable_db;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_root_without_db() {
+        let pool = unreachable_db();
+
+        let response = root(State(pool), State(Arc::new(test_site())))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8_lossy(&body);
 
-pub async fn root() -> impl IntoResponse {
-    let template = RootTemplate {};
-    HtmlTemplate(template)
+        assert!(html.contains("startReadingLink"));
+        assert!(!html.contains("Featured and latest posts"));
+    }
 }
@@ -1,33 +1,120 @@
+use std::sync::OnceLock;
+
 use askama::Template;
-use axum::extract::Form;
 use axum::{extract::State, response::IntoResponse};
+use axum_extra::extract::Form;
 use serde::Deserialize;
 use sqlx::PgPool;
+use time::macros::format_description;
+use time::Date;
 
-use crate::crud::blog::Blog;
+use crate::crud::blog::{Blog, FacetCount, SearchQuery, SortOrder};
+use crate::utils::embedding::{embedder_from_env, Embedder, EmbedderBackend};
 use crate::utils::html::HtmlTemplate;
 
+static EMBEDDER: OnceLock<Option<EmbedderBackend>> = OnceLock::new();
+
 #[derive(Template)]
 #[template(path = "blogs-search-results.html")]
 struct BlogsTemplate {
     blogs: Vec<Blog>,
+    facets: Option<SearchFacets>,
+}
+
+pub struct SearchFacets {
+    pub search_string: String,
+    pub from: String,
+    pub to: String,
+    pub tags: Vec<String>,
+    pub sort: SortOrder,
+    pub tag_facets: Vec<FacetCount>,
+    pub year_facets: Vec<FacetCount>,
+}
+
+impl SearchFacets {
+    pub fn is_selected(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn is_sorted_by(&self, sort: &str) -> bool {
+        match sort {
+            "newest" => self.sort == SortOrder::Newest,
+            "oldest" => self.sort == SortOrder::Oldest,
+            _ => self.sort == SortOrder::Relevance,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Search {
+    #[serde(default)]
     search_string: String,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    sort: SortOrder,
+}
+
+fn parse_date(date: &str) -> Option<Date> {
+    let date_format = format_description!("[year]-[month]-[day]");
+    Date::parse(date, &date_format).ok()
+}
+
+async fn embed_query(search: &str) -> Option<Vec<f32>> {
+    let embedder = EMBEDDER.get_or_init(embedder_from_env).as_ref()?;
+    if search.trim().is_empty() {
+        return None;
+    }
+
+    match embedder.embed(&[search.to_string()]).await {
+        Ok(mut embeddings) => embeddings.pop(),
+        Err(err) => {
+            eprintln!(
+                "Error embedding search query, falling back to keywords: {}",
+                err
+            );
+            None
+        }
+    }
 }
 
 pub async fn search(State(pool): State<PgPool>, Form(body): Form<Search>) -> impl IntoResponse {
-    match Blog::search_blogs(&pool, body.search_string.as_str()).await {
-        Ok(blogs) => {
-            let template = BlogsTemplate { blogs };
+    let query = SearchQuery {
+        search: body.search_string.clone(),
+        from: parse_date(&body.from),
+        to: parse_date(&body.to),
+        tags: body.tags.clone(),
+        sort: body.sort,
+        embedding: embed_query(&body.search_string).await,
+    };
+
+    match Blog::search_blogs(&pool, &query).await {
+        Ok(results) => {
+            let template = BlogsTemplate {
+                blogs: results.blogs,
+                facets: Some(SearchFacets {
+                    search_string: body.search_string,
+                    from: body.from,
+                    to: body.to,
+                    tags: body.tags,
+                    sort: body.sort,
+                    tag_facets: results.tag_facets,
+                    year_facets: results.year_facets,
+                }),
+            };
             HtmlTemplate(template)
         }
         Err(err) => {
             println!("Error fetching blogs: {}", err);
 
-            let error_template = BlogsTemplate { blogs: vec![] };
+            let error_template = BlogsTemplate {
+                blogs: vec![],
+                facets: None,
+            };
             HtmlTemplate(error_template)
         }
     }
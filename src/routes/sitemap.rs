@@ -1,14 +1,127 @@
-use axum::{
-    http::{header, HeaderMap, StatusCode},
-    response::IntoResponse,
-};
-
-pub async fn sitemap_xml() -> impl IntoResponse {
-    let content = include_str!("../../sitemap.xml");
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        header::CONTENT_TYPE,
-        "application/xml; charset=utf-8".parse().unwrap(),
-    );
-    (StatusCode::OK, headers, content)
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+use crate::utils::feed::rfc3339;
+use crate::utils::http::conditional_response;
+use crate::utils::sitemap::{sitemap_urls, SitemapUrl, MAX_SITEMAP_URLS};
+
+const SITEMAP_TTL: Duration = Duration::from_secs(60 * 60);
+
+static SITEMAP: Mutex<Option<(Instant, Arc<Vec<SitemapUrl>>)>> = Mutex::new(None);
+
+#[derive(Template)]
+#[template(path = "sitemap.xml")]
+struct SitemapTemplate<'a> {
+    urls: &'a [SitemapUrl],
+}
+
+#[derive(Template)]
+#[template(path = "sitemap-index.xml")]
+struct SitemapIndexTemplate {
+    // (location, lastmod) of each sitemap
+    sitemaps: Vec<(String, Option<String>)>,
+}
+
+async fn get_urls(pool: &PgPool, site: &SiteConfig) -> Result<Arc<Vec<SitemapUrl>>, Response> {
+    if let Some((built, urls)) = SITEMAP.lock().unwrap().as_ref() {
+        if built.elapsed() < SITEMAP_TTL {
+            return Ok(urls.clone());
+        }
+    }
+
+    match Blog::get_published_blogs(pool, None, None).await {
+        Ok(blogs) => {
+            let urls = Arc::new(sitemap_urls(site, blogs));
+            *SITEMAP.lock().unwrap() = Some((Instant::now(), urls.clone()));
+            Ok(urls)
+        }
+        Err(err) => {
+            eprintln!("Error fetching blogs for sitemap: {}", err);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+fn last_modified(urls: &[SitemapUrl]) -> Option<OffsetDateTime> {
+    urls.iter().filter_map(|url| url.lastmod).max()
+}
+
+fn render(
+    headers: &HeaderMap,
+    template: impl Template,
+    lastmod: Option<OffsetDateTime>,
+) -> Response {
+    match template.render() {
+        Ok(body) => conditional_response(headers, "application/xml; charset=utf-8", body, lastmod),
+        Err(err) => {
+            eprintln!("Failed to render sitemap: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn sitemap_xml(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    headers: HeaderMap,
+) -> Response {
+    let urls = match get_urls(&pool, &site).await {
+        Ok(urls) => urls,
+        Err(response) => return response,
+    };
+
+    if urls.len() <= MAX_SITEMAP_URLS {
+        let template = SitemapTemplate { urls: &urls };
+        return render(&headers, template, last_modified(&urls));
+    }
+
+    let sitemaps = urls
+        .chunks(MAX_SITEMAP_URLS)
+        .enumerate()
+        .map(|(i, chunk)| {
+            (
+                site.url(&format!("/sitemaps/{}.xml", i + 1)),
+                last_modified(chunk).map(rfc3339),
+            )
+        })
+        .collect();
+    render(
+        &headers,
+        SitemapIndexTemplate { sitemaps },
+        last_modified(&urls),
+    )
+}
+
+pub async fn sitemap_page(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let page = file
+        .strip_suffix(".xml")
+        .and_then(|page| page.parse::<usize>().ok())
+        .filter(|page| *page >= 1);
+    let urls = match get_urls(&pool, &site).await {
+        Ok(urls) => urls,
+        Err(response) => return response,
+    };
+
+    match page.and_then(|page| urls.chunks(MAX_SITEMAP_URLS).nth(page - 1)) {
+        Some(chunk) => render(
+            &headers,
+            SitemapTemplate { urls: chunk },
+            last_modified(chunk),
+        ),
+        None => (StatusCode::NOT_FOUND, "nothing to see here").into_response(),
+    }
 }
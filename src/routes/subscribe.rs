@@ -1,22 +1,334 @@
+use std::sync::Arc;
+
+use askama::Template;
 use axum::extract::Form;
-use axum::extract::State;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
 use sqlx::PgPool;
+use time::Duration;
 
 use crate::crud::subscriber::Subscriber;
+use crate::utils::config::SiteConfig;
+use crate::utils::email::normalize_email;
+use crate::utils::html::HtmlTemplate;
+use crate::utils::mailer::{Email, Mailer};
+use crate::utils::token::{hash_token, new_token, UnsubscribeKey};
+
+const CONFIRMATION_TTL: Duration = Duration::hours(48);
+// The form in the footer is the only way to sign up
+const SOURCE: &str = "footer";
+
+/// Outcome of a subscription, swapped into the footer form by htmx.
+#[derive(Template)]
+#[template(path = "subscribe.html")]
+struct SubscribeTemplate {
+    success: bool,
+    message: &'static str,
+}
+
+/// Page shown after following a link from an email.
+#[derive(Template)]
+#[template(path = "subscription.html")]
+struct SubscriptionTemplate {
+    site: Arc<SiteConfig>,
+    heading: &'static str,
+    message: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/confirm.html")]
+struct ConfirmHtml<'a> {
+    site: &'a SiteConfig,
+    confirm_url: &'a str,
+    unsubscribe_url: &'a str,
+    expires_in_hours: i64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/confirm.txt")]
+struct ConfirmText<'a> {
+    site: &'a SiteConfig,
+    confirm_url: &'a str,
+    unsubscribe_url: &'a str,
+    expires_in_hours: i64,
+}
 
 #[derive(Deserialize, Debug)]
 pub struct Subscribe {
+    #[serde(default)]
     email: String,
 }
 
-pub async fn subscribe(State(pool): State<PgPool>, Form(body): Form<Subscribe>) {
-    match Subscriber::create_subscriber(&pool, body.email.as_str()).await {
-        Ok(_) => {
-            println!("New subscriber: {}", body.email)
+fn fragment(status: StatusCode, success: bool, message: &'static str) -> Response {
+    (status, HtmlTemplate(SubscribeTemplate { success, message })).into_response()
+}
+
+pub fn subscription_page(
+    status: StatusCode,
+    site: Arc<SiteConfig>,
+    heading: &'static str,
+    message: &'static str,
+) -> Response {
+    let template = SubscriptionTemplate {
+        site,
+        heading,
+        message,
+    };
+    (status, HtmlTemplate(template)).into_response()
+}
+
+async fn send_confirmation(
+    site: &SiteConfig,
+    mailer: &dyn Mailer,
+    unsubscribe: &UnsubscribeKey,
+    email: &str,
+    token: &str,
+) -> Result<(), String> {
+    let confirm_url = site.url(&format!("/subscribe/confirm/{}", token));
+    let unsubscribe_url = unsubscribe.url(site, email);
+    let expires_in_hours = CONFIRMATION_TTL.whole_hours();
+    let html = ConfirmHtml {
+        site,
+        confirm_url: &confirm_url,
+        unsubscribe_url: &unsubscribe_url,
+        expires_in_hours,
+    };
+    let text = ConfirmText {
+        site,
+        confirm_url: &confirm_url,
+        unsubscribe_url: &unsubscribe_url,
+        expires_in_hours,
+    };
+    let email = Email {
+        to: email.to_string(),
+        subject: format!("Confirm your subscription to {}", site.name),
+        text: text.render().map_err(|err| err.to_string())?,
+        html: html.render().map_err(|err| err.to_string())?,
+        unsubscribe_url,
+    };
+    mailer.send(&email).await
+}
+
+/// Signs the address up as pending and emails it a confirmation link.
+pub async fn subscribe(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    State(mailer): State<Option<Arc<dyn Mailer>>>,
+    State(unsubscribe): State<Arc<UnsubscribeKey>>,
+    Form(body): Form<Subscribe>,
+) -> Response {
+    let Some(mailer) = mailer else {
+        return fragment(
+            StatusCode::SERVICE_UNAVAILABLE,
+            false,
+            "Subscriptions are unavailable right now, please try again later.",
+        );
+    };
+    let Some(email) = normalize_email(&body.email) else {
+        return fragment(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            false,
+            "That doesn't look like an email address.",
+        );
+    };
+
+    let token = new_token();
+    let token_hash = hash_token(&token);
+    let expires_at = OffsetDateTime::now_utc() + CONFIRMATION_TTL;
+    let sign_up =
+        match Subscriber::create_subscriber(&pool, &email, &token_hash, expires_at, SOURCE).await {
+            Ok(sign_up) => sign_up,
+            Err(err) => {
+                eprintln!("Error creating subscriber: {}", err);
+                return fragment(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    false,
+                    "Something went wrong, please try again later.",
+                );
+            }
+        };
+
+    if sign_up.needs_confirmation() {
+        if let Err(err) =
+            send_confirmation(&site, mailer.as_ref(), &unsubscribe, &email, &token).await
+        {
+            eprintln!("Error sending confirmation to {}: {}", email, err);
+            // Otherwise retries would be throttled without an email going out
+            if let Err(err) = Subscriber::confirmation_failed(&pool, &email, &token_hash).await {
+                eprintln!("Error resetting confirmation of {}: {}", email, err);
+            }
+            return fragment(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                false,
+                "The confirmation email couldn't be sent, please try again later.",
+            );
         }
-        Err(error) => {
-            println!("Error fetching blogs: {}", error);
+    }
+
+    // The same answer for confirmed and throttled addresses, so the form
+    // doesn't reveal who is subscribed
+    fragment(
+        StatusCode::OK,
+        true,
+        "Almost there! Check your inbox to confirm your subscription.",
+    )
+}
+
+pub async fn confirm(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(token): Path<String>,
+) -> Response {
+    let (status, heading, message) = match Subscriber::confirm(&pool, &hash_token(&token)).await {
+        Ok(Some(_)) => (
+            StatusCode::OK,
+            "Subscribed",
+            "Thanks for confirming! New posts will show up in your inbox.",
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            "Link expired",
+            "This confirmation link is invalid or has expired. Subscribe again to get a new one.",
+        ),
+        Err(err) => {
+            eprintln!("Error confirming subscriber: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong",
+                "Your subscription couldn't be confirmed, please try again later.",
+            )
         }
+    };
+    subscription_page(status, site, heading, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::config::test_site;
+    use crate::utils::db::{get_db, unreachable_db};
+    use crate::utils::mailer::{FlakyMailer, MemoryMailer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribe_without_mailer() {
+        let response = subscribe(
+            State(unreachable_db()),
+            State(Arc::new(test_site())),
+            State(None),
+            State(Arc::new(UnsubscribeKey::new(b"secret"))),
+            Form(Subscribe {
+                email: String::from("reader@example.com"),
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_subscribe_after_failed_send() {
+        let pool = get_db().await;
+        let email = "unreachable@example.com";
+        let subscribe = |mailer: Arc<dyn Mailer>| {
+            subscribe(
+                State(pool.clone()),
+                State(Arc::new(test_site())),
+                State(Some(mailer)),
+                State(Arc::new(UnsubscribeKey::new(b"secret"))),
+                Form(Subscribe {
+                    email: String::from(email),
+                }),
+            )
+        };
+
+        let flaky = FlakyMailer {
+            inner: MemoryMailer::default(),
+            refuse: email,
+        };
+        let failed = subscribe(Arc::new(flaky)).await;
+        assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        // Trying again right away sends the email
+        let mailer = Arc::new(MemoryMailer::default());
+        let retried = subscribe(mailer.clone()).await;
+        assert_eq!(retried.status(), StatusCode::OK);
+        assert_eq!(mailer.sent.lock().unwrap().len(), 1);
+
+        Subscriber::delete_subscriber(&pool, email).await.unwrap();
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_subscribe() {
+        let pool = get_db().await;
+        let site = Arc::new(test_site());
+        let mailer = Arc::new(MemoryMailer::default());
+        let subscribe = |email: &str| {
+            subscribe(
+                State(pool.clone()),
+                State(site.clone()),
+                State(Some(mailer.clone() as Arc<dyn Mailer>)),
+                State(Arc::new(UnsubscribeKey::new(b"secret"))),
+                Form(Subscribe {
+                    email: String::from(email),
+                }),
+            )
+        };
+
+        let invalid = subscribe("not an email").await;
+        assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let created = subscribe(" Route@Example.com ").await;
+        assert_eq!(created.status(), StatusCode::OK);
+        // Another email only goes out after a while
+        let again = subscribe("route@example.com").await;
+        assert_eq!(again.status(), StatusCode::OK);
+        assert_eq!(mailer.sent.lock().unwrap().len(), 1);
+        Subscriber::backdate_confirmation(&pool, "route@example.com")
+            .await
+            .unwrap();
+        let later = subscribe("route@example.com").await;
+        assert_eq!(later.status(), StatusCode::OK);
+        assert_eq!(
+            Subscriber::get_subscribers(&pool, "route@example.com")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        let subscriber = Subscriber::get_subscriber(&pool, "route@example.com")
+            .await
+            .unwrap();
+        assert_eq!(subscriber.source.as_deref(), Some("footer"));
+
+        // Only the latest link works
+        let sent = mailer.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, "route@example.com");
+        let prefix = "https://www.example.com/subscribe/confirm/";
+        let token = |email: &Email| {
+            let start = email.text.find(prefix).unwrap() + prefix.len();
+            email.text[start..start + 64].to_string()
+        };
+
+        let confirm =
+            |token: String| confirm(State(pool.clone()), State(site.clone()), Path(token));
+        assert_eq!(
+            confirm(token(&sent[0])).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(confirm(token(&sent[1])).await.status(), StatusCode::OK);
+        assert_eq!(
+            subscribe("route@example.com").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(mailer.sent.lock().unwrap().len(), 2);
+
+        Subscriber::delete_subscriber(&pool, "route@example.com")
+            .await
+            .unwrap();
     }
 }
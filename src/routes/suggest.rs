@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use askama::Template;
+use axum::extract::Query;
+use axum::{extract::State, response::IntoResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
+
+use crate::crud::blog::Blog;
+use crate::utils::html::HtmlTemplate;
+use crate::utils::suggest::{PrefixIndex, Suggestion};
+
+// Posts only change with a deployment, which starts new instances, but
+// scheduled posts show up when their date arrives. So the index is built
+// again on the first request of every day.
+type SuggestionCache = Mutex<Option<(Date, Arc<PrefixIndex>)>>;
+
+static SUGGESTION_INDEX: OnceLock<SuggestionCache> = OnceLock::new();
+
+async fn suggestion_index(pool: &PgPool) -> Result<Arc<PrefixIndex>, sqlx::Error> {
+    let today = OffsetDateTime::now_utc().date();
+    let cache = SUGGESTION_INDEX.get_or_init(Default::default);
+    if let Some((built_on, index)) = cache.lock().unwrap().as_ref() {
+        if *built_on == today {
+            return Ok(index.clone());
+        }
+    }
+
+    let mut blogs = Blog::get_all_blogs(pool).await?;
+    blogs.retain(|blog| blog.date <= today);
+    let index = Arc::new(PrefixIndex::from_blogs(&blogs));
+    *cache.lock().unwrap() = Some((today, index.clone()));
+    Ok(index)
+}
+
+#[derive(Template)]
+#[template(path = "search-suggestions.html")]
+struct SuggestionsTemplate {
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Suggest {
+    #[serde(default)]
+    q: String,
+}
+
+pub async fn suggest(
+    State(pool): State<PgPool>,
+    Query(params): Query<Suggest>,
+) -> impl IntoResponse {
+    match suggestion_index(&pool).await {
+        Ok(index) => HtmlTemplate(SuggestionsTemplate {
+            suggestions: index.suggest(&params.q),
+        }),
+        Err(err) => {
+            eprintln!("Error building suggestion index: {}", err);
+            HtmlTemplate(SuggestionsTemplate {
+                suggestions: vec![],
+            })
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::PgPool;
+
+use crate::crud::blog::Blog;
+use crate::routes::search::SearchFacets;
+use crate::utils::config::SiteConfig;
+use crate::utils::feed::tag_feed_url;
+use crate::utils::html::HtmlTemplate;
+
+#[derive(Template)]
+#[template(path = "tag.html")]
+struct TagTemplate {
+    site: Arc<SiteConfig>,
+    tag: String,
+    feed_url: String,
+    blogs: Vec<Blog>,
+    facets: Option<SearchFacets>,
+}
+
+pub async fn tag(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Path(tag): Path<String>,
+) -> Response {
+    match Blog::get_published_blogs(&pool, Some(&tag), None).await {
+        Ok(blogs) if blogs.is_empty() => {
+            (StatusCode::NOT_FOUND, "nothing to see here").into_response()
+        }
+        Ok(blogs) => HtmlTemplate(TagTemplate {
+            feed_url: tag_feed_url(&site, &tag),
+            site,
+            tag,
+            blogs,
+            facets: None,
+        })
+        .into_response(),
+        Err(err) => {
+            eprintln!("Error fetching blogs for tag {}: {}", tag, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Form, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::crud::subscriber::Subscriber;
+use crate::routes::subscribe::subscription_page;
+use crate::utils::config::SiteConfig;
+use crate::utils::html::HtmlTemplate;
+use crate::utils::token::UnsubscribeKey;
+
+const MAX_REASON_CHARS: usize = 500;
+
+#[derive(Template)]
+#[template(path = "unsubscribe.html")]
+struct UnsubscribeTemplate {
+    site: Arc<SiteConfig>,
+    email: String,
+    action: String,
+}
+
+/// Either the form on the confirmation page, or the body mail clients post
+/// for a one-click unsubscribe (RFC 8058).
+#[derive(Deserialize, Debug, Default)]
+pub struct UnsubscribeForm {
+    #[serde(rename = "List-Unsubscribe")]
+    list_unsubscribe: Option<String>,
+    reason: Option<String>,
+}
+
+impl UnsubscribeForm {
+    fn reason(&self) -> Option<String> {
+        let reason = self.reason.as_deref().map(str::trim).unwrap_or_default();
+        if !reason.is_empty() {
+            Some(reason.chars().take(MAX_REASON_CHARS).collect())
+        } else if self.list_unsubscribe.as_deref() == Some("One-Click") {
+            Some(String::from("one-click"))
+        } else {
+            None
+        }
+    }
+}
+
+fn invalid_link(site: Arc<SiteConfig>) -> Response {
+    subscription_page(
+        StatusCode::NOT_FOUND,
+        site,
+        "Link not recognized",
+        "This unsubscribe link isn't valid. Try the link in the most recent email.",
+    )
+}
+
+/// Asks for confirmation, so link scanners fetching the page don't
+/// unsubscribe anyone.
+pub async fn unsubscribe_page(
+    State(site): State<Arc<SiteConfig>>,
+    State(key): State<Arc<UnsubscribeKey>>,
+    Path(token): Path<String>,
+) -> Response {
+    let Some(email) = key.verify(&token) else {
+        return invalid_link(site);
+    };
+    let template = UnsubscribeTemplate {
+        site,
+        email,
+        action: format!("/unsubscribe/{}", token),
+    };
+    HtmlTemplate(template).into_response()
+}
+
+pub async fn unsubscribe(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    State(key): State<Arc<UnsubscribeKey>>,
+    Path(token): Path<String>,
+    form: Option<Form<UnsubscribeForm>>,
+) -> Response {
+    let Some(email) = key.verify(&token) else {
+        return invalid_link(site);
+    };
+    let Form(form) = form.unwrap_or_default();
+
+    match Subscriber::unsubscribe(&pool, &email, form.reason().as_deref()).await {
+        Ok(_) => subscription_page(
+            StatusCode::OK,
+            site,
+            "Unsubscribed",
+            "You won't get any more emails from us. Subscribe again from the footer anytime.",
+        ),
+        Err(err) => {
+            eprintln!("Error unsubscribing {}: {}", email, err);
+            subscription_page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                site,
+                "Something went wrong",
+                "You couldn't be unsubscribed, please try again later.",
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::Router;
+    use sqlx::types::time::OffsetDateTime;
+
+    use crate::utils::config::{test_site, AppState};
+    use crate::utils::db::get_db;
+    use crate::utils::mailer::MemoryMailer;
+
+    use super::*;
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_unsubscribe() {
+        let pool = get_db().await;
+        let site = Arc::new(test_site());
+        let key = Arc::new(UnsubscribeKey::new(b"secret"));
+        let email = "leaving@example.com";
+        Subscriber::create_subscriber(&pool, email, "leaving", OffsetDateTime::now_utc(), "test")
+            .await
+            .unwrap();
+
+        let token = key.token(email);
+        let page =
+            unsubscribe_page(State(site.clone()), State(key.clone()), Path(token.clone())).await;
+        assert_eq!(page.status(), StatusCode::OK);
+        let forged = unsubscribe_page(
+            State(site.clone()),
+            State(key.clone()),
+            Path(UnsubscribeKey::new(b"other").token(email)),
+        )
+        .await;
+        assert_eq!(forged.status(), StatusCode::NOT_FOUND);
+
+        // As sent by mail clients for List-Unsubscribe-Post
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route(
+                "/unsubscribe/:token",
+                get(unsubscribe_page).post(unsubscribe),
+            )
+            .with_state(AppState {
+                pool: pool.clone(),
+                site,
+                mailer: Some(Arc::new(MemoryMailer::default())),
+                unsubscribe: key,
+            });
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/unsubscribe/{}", address, token))
+            .form(&[("List-Unsubscribe", "One-Click")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+
+        let reason = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT unsubscribe_reason FROM subscriber WHERE email = $1 AND status = 'unsubscribed'",
+        )
+        .bind(email)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(reason.as_deref(), Some("one-click"));
+
+        Subscriber::delete_subscriber(&pool, email).await.unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::crud::{blog::Blog, webmention::Webmention};
+use crate::utils::config::SiteConfig;
+use crate::utils::webmention::validate;
+
+#[derive(Deserialize, Debug)]
+pub struct WebmentionForm {
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    target: String,
+}
+
+/// [Webmention](https://www.w3.org/TR/webmention/) receiver. Mentions are
+/// queued with 202 Accepted and verified by the scheduled tasks.
+pub async fn webmention(
+    State(pool): State<PgPool>,
+    State(site): State<Arc<SiteConfig>>,
+    Form(form): Form<WebmentionForm>,
+) -> impl IntoResponse {
+    if !site.features.webmentions {
+        return (StatusCode::NOT_FOUND, String::from("nothing to see here"));
+    }
+    let blog_id = match validate(&form.source, &form.target, &site.base_url) {
+        Ok(blog_id) => blog_id,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    if Blog::get_blog(&pool, blog_id).await.is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            String::from("target must be a post on this site"),
+        );
+    }
+
+    if let Err(err) = Webmention::enqueue(&pool, &form.source, &form.target, blog_id).await {
+        eprintln!("Error queueing webmention from {}: {}", form.source, err);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("webmention could not be queued"),
+        );
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        String::from("webmention queued for verification"),
+    )
+}
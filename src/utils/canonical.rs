@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use reqwest::Url;
+
+use crate::utils::config::SiteConfig;
+use crate::utils::robots::is_production;
+
+/// Query parameters that only track where a visitor came from.
+const TRACKING_PARAMS: [&str; 7] = [
+    "fbclid", "gclid", "msclkid", "dclid", "igshid", "mc_cid", "mc_eid",
+];
+
+/// Static files are served from disk, where names are case sensitive, and
+/// tags are matched exactly. Only the prefix itself is lowercased.
+const CASE_SENSITIVE_PREFIXES: [&str; 2] = ["/assets/", "/tags/"];
+
+/// Lowercases the path, collapses leading slashes so it can't turn into a
+/// protocol relative URL, and drops trailing slashes, except for `/`.
+pub fn normalize_path(path: &str) -> String {
+    // Browsers read a backslash as a slash
+    let path = format!("/{}", path.trim_start_matches(['/', '\\']));
+    let prefix = CASE_SENSITIVE_PREFIXES.iter().find(|prefix| {
+        path.get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    });
+    let path = match prefix {
+        Some(prefix) => format!("{}{}", prefix, &path[prefix.len()..]),
+        None => path.to_lowercase(),
+    };
+    match path.trim_end_matches('/') {
+        "" => String::from("/"),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+fn is_tracking_param(pair: &str) -> bool {
+    let name = pair.split('=').next().unwrap_or_default().to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+/// The query without tracking parameters, or `None` when nothing is left.
+pub fn strip_tracking(query: &str) -> Option<String> {
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !is_tracking_param(pair))
+        .collect::<Vec<_>>()
+        .join("&");
+    (!query.is_empty()).then_some(query)
+}
+
+/// Where the site is expected to be reached, and whether requests on
+/// another host or scheme get sent there.
+pub struct CanonicalOrigin {
+    base: Url,
+    enforce: bool,
+}
+
+impl CanonicalOrigin {
+    pub fn new(base: &str, enforce: bool) -> Option<CanonicalOrigin> {
+        let base = Url::parse(base).ok()?;
+        base.host_str()?;
+        Some(CanonicalOrigin { base, enforce })
+    }
+
+    /// The site's base URL, enforced in production only so other
+    /// environments keep answering on their own hosts.
+    pub fn from_site(site: &SiteConfig) -> Option<CanonicalOrigin> {
+        CanonicalOrigin::new(&site.base_url, is_production())
+    }
+
+    fn url(&self, path: &str, query: Option<&str>) -> String {
+        let mut url = self.base.clone();
+        url.set_path(path);
+        url.set_query(query);
+        url.to_string()
+    }
+
+    /// The permanent redirect for a request that isn't on its canonical URL,
+    /// absolute on the canonical origin when it's enforced and on the
+    /// request's own host otherwise. The query is kept as is, so analytics
+    /// still see the tracking parameters.
+    pub fn redirect(&self, headers: &HeaderMap, uri: &Uri) -> Option<String> {
+        let path = normalize_path(uri.path());
+        let host = headers
+            .get("X-Forwarded-Host")
+            .or_else(|| headers.get(header::HOST))
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| uri.host());
+        let scheme = headers
+            .get("X-Forwarded-Proto")
+            .and_then(|scheme| scheme.to_str().ok())
+            .or_else(|| uri.scheme_str());
+
+        // Hosts and schemes the request doesn't tell us about are assumed fine
+        let wrong_host = host.is_some_and(|host| {
+            !host
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .eq_ignore_ascii_case(self.base.host_str().unwrap_or_default())
+        });
+        let wrong_scheme =
+            scheme.is_some_and(|scheme| !scheme.eq_ignore_ascii_case(self.base.scheme()));
+
+        let wrong_path = path != uri.path();
+        if self.enforce && (wrong_host || wrong_scheme || wrong_path) {
+            Some(self.url(&path, uri.query()))
+        } else if wrong_path {
+            Some(match uri.query() {
+                Some(query) => format!("{}?{}", path, query),
+                None => path,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The URL search engines should index the response under.
+    pub fn canonical_link(&self, uri: &Uri) -> String {
+        let query = uri.query().and_then(strip_tracking);
+        self.url(&normalize_path(uri.path()), query.as_deref())
+    }
+}
+
+/// Middleware that 301-redirects GET and HEAD requests to their canonical
+/// URL and marks successful responses with a canonical `Link` header.
+pub async fn canonicalize(
+    State(site): State<Arc<SiteConfig>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = CanonicalOrigin::from_site(&site) else {
+        eprintln!("The site's base URL isn't valid, skipping URL canonicalization");
+        return next.run(request).await;
+    };
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+
+    if let Some(location) = origin.redirect(request.headers(), request.uri()) {
+        return (
+            StatusCode::MOVED_PERMANENTLY,
+            [(header::LOCATION, location)],
+        )
+            .into_response();
+    }
+
+    let link = format!(
+        "<{}>; rel=\"canonical\"",
+        origin.canonical_link(request.uri())
+    );
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::OK {
+        if let Ok(link) = HeaderValue::from_str(&link) {
+            response.headers_mut().insert(header::LINK, link);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(host: &str, scheme: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, host.parse().unwrap());
+        headers.insert("X-Forwarded-Proto", scheme.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("/health/"), "/health");
+        assert_eq!(normalize_path("/Blog/4//"), "/blog/4");
+        assert_eq!(normalize_path("//Evil.com/"), "/evil.com");
+        assert_eq!(normalize_path("//"), "/");
+        assert_eq!(normalize_path("/\\evil.com"), "/evil.com");
+        assert_eq!(
+            normalize_path("/Assets/images/Diagram.png"),
+            "/assets/images/Diagram.png"
+        );
+        assert_eq!(normalize_path("/Tags/Rust/"), "/tags/Rust");
+        assert_eq!(normalize_path("/tags/caf%C3%A9"), "/tags/caf%C3%A9");
+        assert_eq!(
+            strip_tracking("page=2&utm_source=x&UTM_Medium=y&fbclid=z").as_deref(),
+            Some("page=2")
+        );
+        assert_eq!(strip_tracking("utm_campaign=launch"), None);
+    }
+
+    #[test]
+    fn test_redirect() {
+        let origin = CanonicalOrigin::new("https://www.example.com", true).unwrap();
+        let uri = |uri: &str| uri.parse::<Uri>().unwrap();
+
+        let canonical = headers("www.example.com", "https");
+        assert_eq!(
+            origin.redirect(&canonical, &uri("/blog/4?utm_source=x")),
+            None
+        );
+        assert_eq!(
+            origin
+                .redirect(&canonical, &uri("/Blogs/?page=2"))
+                .as_deref(),
+            Some("https://www.example.com/blogs?page=2")
+        );
+        assert_eq!(
+            origin.redirect(&canonical, &uri("//evil.com/")).as_deref(),
+            Some("https://www.example.com/evil.com")
+        );
+        assert_eq!(
+            origin
+                .redirect(&headers("example.com", "https"), &uri("/blog/4"))
+                .as_deref(),
+            Some("https://www.example.com/blog/4")
+        );
+        assert_eq!(
+            origin
+                .redirect(&headers("www.example.com", "http"), &uri("/"))
+                .as_deref(),
+            Some("https://www.example.com/")
+        );
+
+        let staging = CanonicalOrigin::new("https://www.example.com", false).unwrap();
+        let local = headers("localhost:3000", "http");
+        assert_eq!(staging.redirect(&local, &uri("/blog/4")), None);
+        assert_eq!(
+            staging.redirect(&local, &uri("/health/?a=1")).as_deref(),
+            Some("/health?a=1")
+        );
+        assert_eq!(
+            staging.redirect(&local, &uri("//evil.com/")).as_deref(),
+            Some("/evil.com")
+        );
+
+        assert_eq!(
+            origin.canonical_link(&uri("/blogs?page=2&utm_source=x")),
+            "https://www.example.com/blogs?page=2"
+        );
+    }
+}
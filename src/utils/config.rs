@@ -0,0 +1,328 @@
+use std::env;
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::utils::mailer::Mailer;
+use crate::utils::token::UnsubscribeKey;
+
+// Embedded, as the Lambda deployment only ships the binary
+const SETTINGS: &str = include_str!("../../settings.yaml");
+
+/// Identity and behaviour of the site, from the `site` section of
+/// `settings.yaml`. Every value can be overridden from the environment, see
+/// [`SiteConfig::apply_overrides`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct SiteConfig {
+    pub name: String,
+    pub author: String,
+    /// Title of the landing page.
+    pub title: String,
+    pub description: String,
+    /// Scheme and host the site is served on, without a trailing slash.
+    pub base_url: String,
+    pub job_title: String,
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub social: Social,
+    #[serde(default)]
+    pub features: Features,
+    pub resume: Resume,
+}
+
+/// Handles on other sites, linked from the header and structured data.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Social {
+    pub github: Option<String>,
+    pub stackoverflow: Option<String>,
+    pub linkedin: Option<String>,
+    pub lastfm: Option<String>,
+    pub spotify: Option<String>,
+    pub imdb: Option<String>,
+    pub twitter: Option<String>,
+}
+
+fn profile(handle: &Option<String>, format: impl Fn(&str) -> String) -> Option<String> {
+    handle.as_deref().map(format)
+}
+
+impl Social {
+    pub fn github_url(&self) -> Option<String> {
+        profile(&self.github, |h| format!("https://github.com/{}", h))
+    }
+
+    pub fn stackoverflow_url(&self) -> Option<String> {
+        profile(&self.stackoverflow, |h| {
+            format!("https://stackoverflow.com/users/{}", h)
+        })
+    }
+
+    pub fn linkedin_url(&self) -> Option<String> {
+        profile(&self.linkedin, |h| {
+            format!("https://www.linkedin.com/in/{}/", h)
+        })
+    }
+
+    pub fn lastfm_url(&self) -> Option<String> {
+        profile(&self.lastfm, |h| format!("https://www.last.fm/user/{}", h))
+    }
+
+    pub fn spotify_url(&self) -> Option<String> {
+        profile(&self.spotify, |h| {
+            format!("https://open.spotify.com/user/{}", h)
+        })
+    }
+
+    pub fn imdb_url(&self) -> Option<String> {
+        profile(&self.imdb, |h| format!("https://www.imdb.com/user/{}/", h))
+    }
+
+    pub fn twitter_url(&self) -> Option<String> {
+        profile(&self.twitter, |h| format!("https://twitter.com/{}", h))
+    }
+
+    /// Professional profiles, for `sameAs` in structured data.
+    pub fn same_as(&self) -> Vec<String> {
+        [
+            self.github_url(),
+            self.linkedin_url(),
+            self.stackoverflow_url(),
+            self.twitter_url(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Features {
+    #[serde(default = "enabled")]
+    pub webmentions: bool,
+    #[serde(default = "enabled")]
+    pub indexnow: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            webmentions: true,
+            indexnow: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Period {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct School {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Experience {
+    pub employer: String,
+    pub title: String,
+    pub period: Period,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Study {
+    pub school: School,
+    pub title: String,
+    pub period: Period,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Resume {
+    pub experience: Vec<Experience>,
+    pub education: Vec<Study>,
+}
+
+#[derive(Deserialize)]
+struct Settings {
+    default: Profile,
+}
+
+#[derive(Deserialize)]
+struct Profile {
+    site: SiteConfig,
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+impl SiteConfig {
+    /// Loads the settings and applies overrides from the environment.
+    pub fn load() -> SiteConfig {
+        SiteConfig::from_yaml(SETTINGS, |name| env::var(name).ok())
+            .expect("settings.yaml must have a valid site section")
+    }
+
+    pub fn from_yaml(
+        yaml: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<SiteConfig, serde_yaml::Error> {
+        let mut site = serde_yaml::from_str::<Settings>(yaml)?.default.site;
+        site.apply_overrides(var);
+        Ok(site)
+    }
+
+    /// Overrides settings with `SITE_URL`, `SITE_NAME`, `SITE_AUTHOR`,
+    /// `SITE_TITLE`, `SITE_DESCRIPTION`, `SITE_KEYWORDS` (comma separated),
+    /// `FEATURE_WEBMENTIONS` and `FEATURE_INDEXNOW`.
+    fn apply_overrides(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let strings = [
+            ("SITE_URL", &mut self.base_url),
+            ("SITE_NAME", &mut self.name),
+            ("SITE_AUTHOR", &mut self.author),
+            ("SITE_TITLE", &mut self.title),
+            ("SITE_DESCRIPTION", &mut self.description),
+        ];
+        for (name, field) in strings {
+            if let Some(value) = var(name) {
+                *field = value;
+            }
+        }
+        if let Some(keywords) = var("SITE_KEYWORDS") {
+            self.keywords = keywords
+                .split(',')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(String::from)
+                .collect();
+        }
+
+        let features = [
+            ("FEATURE_WEBMENTIONS", &mut self.features.webmentions),
+            ("FEATURE_INDEXNOW", &mut self.features.indexnow),
+        ];
+        for (name, field) in features {
+            match var(name).as_deref().map(parse_bool) {
+                Some(Some(value)) => *field = value,
+                Some(None) => eprintln!("Ignoring {}, expected true or false", name),
+                None => {}
+            }
+        }
+
+        self.base_url = self.base_url.trim_end_matches('/').to_string();
+    }
+
+    /// Absolute URL of a path on the site.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn post_url(&self, id: i32) -> String {
+        self.url(&format!("/blog/{}", id))
+    }
+
+    /// The host without a scheme, as shown on Open Graph cards.
+    pub fn domain(&self) -> &str {
+        self.base_url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+    }
+
+    /// The default keywords, after the given tags.
+    pub fn keywords_with(&self, tags: &[String]) -> String {
+        tags.iter()
+            .chain(&self.keywords)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// State shared by the handlers. Handlers extract the parts they need, the
+/// pool, the site config, the mailer or the unsubscribe key, through
+/// [`FromRef`]. The mailer is `None` when emails are turned off.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub site: Arc<SiteConfig>,
+    pub mailer: Option<Arc<dyn Mailer>>,
+    pub unsubscribe: Arc<UnsubscribeKey>,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> PgPool {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SiteConfig> {
+    fn from_ref(state: &AppState) -> Arc<SiteConfig> {
+        state.site.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<dyn Mailer>> {
+    fn from_ref(state: &AppState) -> Option<Arc<dyn Mailer>> {
+        state.mailer.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<UnsubscribeKey> {
+    fn from_ref(state: &AppState) -> Arc<UnsubscribeKey> {
+        state.unsubscribe.clone()
+    }
+}
+
+/// The settings without environment overrides, on `https://www.example.com`.
+#[cfg(test)]
+pub fn test_site() -> SiteConfig {
+    SiteConfig::from_yaml(SETTINGS, |name| {
+        (name == "SITE_URL").then(|| String::from("https://www.example.com"))
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_config() {
+        let site = SiteConfig::from_yaml(SETTINGS, |_| None).unwrap();
+        assert_eq!(site.base_url, "https://www.danielsteman.com");
+        assert_eq!(site.domain(), "www.danielsteman.com");
+        assert!(site.features.webmentions);
+        assert!(!site.resume.experience.is_empty());
+        assert_eq!(
+            site.social.github_url().as_deref(),
+            Some("https://github.com/danielsteman")
+        );
+
+        let overridden = SiteConfig::from_yaml(SETTINGS, |name| match name {
+            "SITE_URL" => Some(String::from("https://staging.example.com/")),
+            "SITE_KEYWORDS" => Some(String::from("rust, ,web")),
+            "FEATURE_WEBMENTIONS" => Some(String::from("false")),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(overridden.post_url(4), "https://staging.example.com/blog/4");
+        assert_eq!(
+            overridden.keywords_with(&[String::from("nix")]),
+            "nix, rust, web"
+        );
+        assert!(!overridden.features.webmentions);
+        assert!(overridden.features.indexnow);
+    }
+}
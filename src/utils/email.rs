@@ -0,0 +1,65 @@
+/// Addresses longer than this can't be delivered to, see RFC 5321.
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_LOCAL_LEN: usize = 64;
+
+fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// A pragmatic syntax check: an unquoted local part and a domain with a
+/// top level domain. Whether the address exists is only known once mail
+/// to it is delivered.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= MAX_LOCAL_LEN
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| is_valid_label(label))
+        && labels
+            .last()
+            .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_alphabetic()));
+
+    email.len() <= MAX_EMAIL_LEN && local_ok && domain_ok
+}
+
+/// The trimmed, lowercased address, or `None` when it isn't valid.
+pub fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    is_valid_email(&email).then_some(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email("  Reader@Example.COM ").as_deref(),
+            Some("reader@example.com")
+        );
+        assert!(is_valid_email("first.last+news@mail.example.co.uk"));
+        assert!(!is_valid_email("reader"));
+        assert!(!is_valid_email("reader@localhost"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("reader@@example.com"));
+        assert!(!is_valid_email("read..er@example.com"));
+        assert!(!is_valid_email("reader@-example.com"));
+        assert!(!is_valid_email("reader@example.c0m"));
+        assert!(!is_valid_email("read er@example.com"));
+        assert!(!is_valid_email(&format!("{}@example.com", "a".repeat(65))));
+    }
+}
@@ -0,0 +1,293 @@
+use std::env;
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::utils::similarity::tokenize;
+
+pub const EMBEDDING_DIMENSIONS: usize = 256;
+const CHUNK_SIZE: usize = 1200;
+const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Debug)]
+pub enum EmbedError {
+    Http(reqwest::Error),
+    InvalidResponse(String),
+}
+
+impl fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbedError::Http(err) => write!(f, "embedding request failed: {}", err),
+            EmbedError::InvalidResponse(msg) => write!(f, "invalid embedding response: {}", msg),
+        }
+    }
+}
+
+impl From<reqwest::Error> for EmbedError {
+    fn from(err: reqwest::Error) -> Self {
+        EmbedError::Http(err)
+    }
+}
+
+pub trait Embedder {
+    /// Returns one `EMBEDDING_DIMENSIONS` long vector per input text.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbedError>;
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is specified, so
+/// stored embeddings stay valid across Rust releases.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Deterministic embedder that hashes tokens into a fixed number of buckets.
+/// It only captures word overlap, but needs no network and is stable across runs.
+pub struct HashingEmbedder;
+
+impl HashingEmbedder {
+    fn embed_one(text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; EMBEDDING_DIMENSIONS];
+        for token in tokenize(text) {
+            let hash = fnv1a(&token);
+            let bucket = (hash % EMBEDDING_DIMENSIONS as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbedError> {
+        Ok(texts.iter().map(|text| Self::embed_one(text)).collect())
+    }
+}
+
+/// Client for an OpenAI compatible `/embeddings` endpoint.
+pub struct HttpEmbedder {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl HttpEmbedder {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> HttpEmbedder {
+        HttpEmbedder {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+}
+
+impl Embedder for HttpEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbedError> {
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&json!({
+                "model": self.model,
+                "input": texts,
+                "dimensions": EMBEDDING_DIMENSIONS,
+            }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let mut response: EmbeddingResponse =
+            request.send().await?.error_for_status()?.json().await?;
+        response.data.sort_by_key(|data| data.index);
+
+        if response.data.len() != texts.len() {
+            return Err(EmbedError::InvalidResponse(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                response.data.len()
+            )));
+        }
+
+        response
+            .data
+            .into_iter()
+            .map(|data| match data.embedding.len() {
+                EMBEDDING_DIMENSIONS => Ok(data.embedding),
+                n => Err(EmbedError::InvalidResponse(format!(
+                    "expected {} dimensions, got {}",
+                    EMBEDDING_DIMENSIONS, n
+                ))),
+            })
+            .collect()
+    }
+}
+
+pub enum EmbedderBackend {
+    Hashing(HashingEmbedder),
+    Http(HttpEmbedder),
+}
+
+impl Embedder for EmbedderBackend {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbedError> {
+        match self {
+            EmbedderBackend::Hashing(embedder) => embedder.embed(texts).await,
+            EmbedderBackend::Http(embedder) => embedder.embed(texts).await,
+        }
+    }
+}
+
+/// Picks the embedder from `EMBEDDINGS_BACKEND` (`http` or `hashing`). Semantic
+/// search is disabled when it isn't set, or when `http` lacks `EMBEDDINGS_URL`.
+pub fn embedder_from_env() -> Option<EmbedderBackend> {
+    match env::var("EMBEDDINGS_BACKEND").ok()?.as_str() {
+        "hashing" => Some(EmbedderBackend::Hashing(HashingEmbedder)),
+        "http" => {
+            let Ok(base_url) = env::var("EMBEDDINGS_URL") else {
+                eprintln!("EMBEDDINGS_URL isn't set, semantic search disabled");
+                return None;
+            };
+            let model = env::var("EMBEDDINGS_MODEL")
+                .unwrap_or_else(|_| DEFAULT_EMBEDDING_MODEL.to_string());
+            let api_key = env::var("OPENAI_API_KEY").ok();
+            Some(EmbedderBackend::Http(HttpEmbedder::new(
+                &base_url, &model, api_key,
+            )))
+        }
+        other => {
+            eprintln!(
+                "Unknown EMBEDDINGS_BACKEND {}, semantic search disabled",
+                other
+            );
+            None
+        }
+    }
+}
+
+/// Splits markdown into chunks of whole paragraphs of roughly `CHUNK_SIZE` characters.
+pub fn chunk_text(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !current.is_empty() && current.len() + paragraph.len() > CHUNK_SIZE {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Cosine similarity of two embeddings, 0 when either is all zeros.
+pub fn embedding_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match norm(a) * norm(b) {
+        0.0 => 0.0,
+        norms => dot / norms,
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+
+    use super::*;
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_hashing_embedder() {
+        let texts = vec![
+            "deploying python services with nix flakes".to_string(),
+            "nix flakes for python deployments".to_string(),
+            "building a split keyboard".to_string(),
+        ];
+        let embeddings = HashingEmbedder.embed(&texts).await.unwrap();
+        let again = HashingEmbedder.embed(&texts).await.unwrap();
+
+        assert_eq!(embeddings, again);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(embeddings[0].len(), EMBEDDING_DIMENSIONS);
+        assert!((dot(&embeddings[0], &embeddings[0]) - 1.0).abs() < 1e-5);
+        assert!(dot(&embeddings[0], &embeddings[1]) > dot(&embeddings[0], &embeddings[2]));
+    }
+
+    #[tokio::test]
+    async fn test_http_embedder() {
+        async fn embeddings(Json(body): Json<Value>) -> Json<Value> {
+            let inputs = body["input"].as_array().unwrap();
+            let data: Vec<Value> = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, _)| {
+                    let mut embedding = vec![0.0; EMBEDDING_DIMENSIONS];
+                    embedding[index] = 1.0;
+                    json!({ "index": index, "embedding": embedding })
+                })
+                .collect();
+            Json(json!({ "data": data }))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().route("/v1/embeddings", post(embeddings));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let embedder = HttpEmbedder::new(&format!("http://{}/v1/", address), "mock", None);
+        let embeddings = embedder
+            .embed(&["first".to_string(), "second".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[0][0], 1.0);
+        assert_eq!(embeddings[1][1], 1.0);
+    }
+
+    #[test]
+    fn test_chunk_text() {
+        let paragraph = "word ".repeat(100);
+        let text = format!("{}\n\n{}\n\n{}\n\n\n", paragraph, paragraph, paragraph);
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_SIZE));
+        assert_eq!(embedding_similarity(&[0.5, 0.0], &[2.0, 0.0]), 1.0);
+        assert_eq!(embedding_similarity(&[1.0, 0.0], &[0.0, 0.0]), 0.0);
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use reqwest::Url;
+use serde::Serialize;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::OffsetDateTime;
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+
+pub const FEED_POSTS: i64 = 20;
+
+static IMAGE_SRC: OnceLock<Regex> = OnceLock::new();
+static URL_ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+
+/// A post prepared for the feeds, with absolute URLs and the body rendered
+/// to HTML.
+pub struct FeedEntry {
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub images: Vec<String>,
+    pub published: OffsetDateTime,
+    pub updated: OffsetDateTime,
+}
+
+impl FeedEntry {
+    pub fn from_blog(blog: Blog, site: &SiteConfig) -> FeedEntry {
+        let url = site.post_url(blog.id);
+        let updated = blog.modified_at();
+        let content = absolute_urls(&Blog::text_to_html(blog.body), &url);
+        let images = IMAGE_SRC
+            .get_or_init(|| Regex::new(r#"<img[^>]*\ssrc="([^"]+)""#).unwrap())
+            .captures_iter(&content)
+            .map(|captures| captures[1].to_string())
+            .collect();
+
+        FeedEntry {
+            url,
+            title: blog.title,
+            summary: blog.summary,
+            content,
+            tags: blog.tags,
+            images,
+            published: blog.date.midnight().assume_utc(),
+            updated,
+        }
+    }
+
+    /// The first image in the post, used as its cover.
+    pub fn image(&self) -> Option<&String> {
+        self.images.first()
+    }
+
+    pub fn published_rfc2822(&self) -> String {
+        self.published.format(&Rfc2822).unwrap_or_default()
+    }
+
+    pub fn published_rfc3339(&self) -> String {
+        rfc3339(self.published)
+    }
+
+    pub fn updated_rfc3339(&self) -> String {
+        rfc3339(self.updated)
+    }
+}
+
+/// Resolves relative links and images against the post URL, as feed readers
+/// can't resolve them.
+fn absolute_urls(html: &str, base: &str) -> String {
+    let Ok(base) = Url::parse(base) else {
+        return html.to_string();
+    };
+    URL_ATTRIBUTE
+        .get_or_init(|| Regex::new(r#"\s(href|src)="([^"]*)""#).unwrap())
+        .replace_all(html, |captures: &Captures| match base.join(&captures[2]) {
+            Ok(url) => format!(" {}=\"{}\"", &captures[1], url),
+            Err(_) => captures[0].to_string(),
+        })
+        .into_owned()
+}
+
+fn image_mime_type(url: &str) -> Option<&'static str> {
+    let extension = url.rsplit('.').next()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+pub fn rfc3339(datetime: OffsetDateTime) -> String {
+    datetime.format(&Rfc3339).unwrap_or_default()
+}
+
+/// When the feed last changed: the latest update of any of its entries.
+pub fn last_updated(entries: &[FeedEntry]) -> Option<OffsetDateTime> {
+    entries.iter().map(|entry| entry.updated).max()
+}
+
+pub fn tag_url(site: &SiteConfig, tag: &str) -> String {
+    match Url::parse(&site.base_url) {
+        Ok(mut url) => {
+            if let Ok(mut segments) = url.path_segments_mut() {
+                segments.pop_if_empty().extend(["tags", tag]);
+            }
+            url.to_string()
+        }
+        Err(_) => site.url(&format!("/tags/{}", tag)),
+    }
+}
+
+/// URL of the RSS feed of posts with `tag`.
+pub fn tag_feed_url(site: &SiteConfig, tag: &str) -> String {
+    format!("{}/feed.xml", tag_url(site, tag))
+}
+
+/// A [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/) document.
+#[derive(Serialize, Debug)]
+pub struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    description: String,
+    language: &'static str,
+    authors: Vec<JsonFeedAuthor>,
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonFeedAuthor {
+    name: String,
+    url: String,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    content_html: String,
+    summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    date_published: String,
+    date_modified: String,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<JsonFeedAttachment>,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonFeedAttachment {
+    url: String,
+    mime_type: &'static str,
+}
+
+impl JsonFeed {
+    pub fn new(site: &SiteConfig, entries: Vec<FeedEntry>) -> JsonFeed {
+        let items = entries
+            .into_iter()
+            .map(|entry| JsonFeedItem {
+                date_published: entry.published_rfc3339(),
+                date_modified: entry.updated_rfc3339(),
+                image: entry.image().cloned(),
+                attachments: entry
+                    .image()
+                    .into_iter()
+                    .filter_map(|image| {
+                        Some(JsonFeedAttachment {
+                            url: image.clone(),
+                            mime_type: image_mime_type(image)?,
+                        })
+                    })
+                    .collect(),
+                id: entry.url.clone(),
+                url: entry.url,
+                title: entry.title,
+                content_html: entry.content,
+                summary: entry.summary,
+                tags: entry.tags,
+            })
+            .collect();
+
+        JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: site.name.clone(),
+            home_page_url: site.url("/"),
+            feed_url: site.url("/feed.json"),
+            description: site.description.clone(),
+            language: "en",
+            authors: vec![JsonFeedAuthor {
+                name: site.author.clone(),
+                url: site.base_url.clone(),
+            }],
+            items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use crate::crud::blog::test_blog;
+    use crate::utils::config::test_site;
+
+    use super::*;
+
+    fn entry() -> FeedEntry {
+        let blog = Blog {
+            body: String::from(
+                "See [this](/blog/4) and ![chart](../assets/chart.png) or [top](#top).",
+            ),
+            date: date!(2024 - 02 - 13),
+            tags: vec![String::from("web")],
+            updated_at: datetime!(2024-03-01 12:00:00 UTC),
+            ..test_blog(12)
+        };
+        FeedEntry::from_blog(blog, &test_site())
+    }
+
+    #[test]
+    fn test_feed_entry() {
+        let entry = entry();
+
+        assert_eq!(entry.url, "https://www.example.com/blog/12");
+        assert!(entry
+            .content
+            .contains("href=\"https://www.example.com/blog/4\""));
+        assert!(entry
+            .content
+            .contains("href=\"https://www.example.com/blog/12#top\""));
+        assert_eq!(
+            entry.image().map(String::as_str),
+            Some("https://www.example.com/assets/chart.png")
+        );
+        assert_eq!(entry.published_rfc2822(), "Tue, 13 Feb 2024 00:00:00 +0000");
+        assert_eq!(entry.updated_rfc3339(), "2024-03-01T12:00:00Z");
+        assert_eq!(
+            last_updated(&[entry]),
+            Some(datetime!(2024-03-01 12:00:00 UTC))
+        );
+        assert_eq!(
+            tag_feed_url(&test_site(), "machine learning"),
+            "https://www.example.com/tags/machine%20learning/feed.xml"
+        );
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let feed = serde_json::to_value(JsonFeed::new(&test_site(), vec![entry()])).unwrap();
+        let item = &feed["items"][0];
+
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(item["summary"], "Summary 12");
+        assert_eq!(item["date_published"], "2024-02-13T00:00:00Z");
+        assert_eq!(item["attachments"][0]["mime_type"], "image/png");
+        assert_eq!(item["attachments"][0]["url"], item["image"]);
+    }
+}
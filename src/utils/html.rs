@@ -4,6 +4,8 @@ use axum::{
     response::{Html, IntoResponse, Response},
 };
 
+use crate::crud::blog::Blog;
+
 pub struct HtmlTemplate<T>(pub T);
 
 impl<T> IntoResponse for HtmlTemplate<T>
@@ -21,3 +23,53 @@ where
         }
     }
 }
+
+/// Spreads cards over columns so their heights stay roughly even. Each card goes
+/// to the currently shortest column, so reading left to right follows the
+/// original order. The index of each card in `blogs` is kept for its CSS `order`.
+pub fn balance_columns(blogs: &[Blog], columns: usize) -> Vec<Vec<(usize, &Blog)>> {
+    let mut balanced: Vec<Vec<(usize, &Blog)>> = vec![vec![]; columns.max(1)];
+    let mut heights = vec![0; balanced.len()];
+
+    for (i, blog) in blogs.iter().enumerate() {
+        let shortest = (0..heights.len()).min_by_key(|&c| heights[c]).unwrap();
+        heights[shortest] += card_height(blog);
+        balanced[shortest].push((i, blog));
+    }
+
+    balanced
+}
+
+// Rough estimate of the rendered card height, in characters of text
+fn card_height(blog: &Blog) -> usize {
+    const LINE_OVERHEAD: usize = 40;
+    blog.title.chars().count()
+        + blog.summary.chars().count()
+        + LINE_OVERHEAD * (3 + blog.tags.len().div_ceil(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crud::blog::test_blog;
+
+    use super::*;
+
+    #[test]
+    fn test_balance_columns() {
+        let blog = |id: i32, summary_len: usize| Blog {
+            summary: "x".repeat(summary_len),
+            ..test_blog(id)
+        };
+        let blogs = vec![blog(1, 600), blog(2, 10), blog(3, 10), blog(4, 10)];
+
+        let columns = balance_columns(&blogs, 2);
+        let ids: Vec<Vec<i32>> = columns
+            .iter()
+            .map(|column| column.iter().map(|(_, b)| b.id).collect())
+            .collect();
+
+        assert_eq!(ids, vec![vec![1], vec![2, 3, 4]]);
+        assert_eq!(columns.iter().map(Vec::len).sum::<usize>(), blogs.len());
+        assert_eq!(balance_columns(&blogs, 1)[0].len(), blogs.len());
+    }
+}
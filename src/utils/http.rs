@@ -0,0 +1,127 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+/// Formats a timestamp as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn http_date(datetime: OffsetDateTime) -> String {
+    let format = format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+    datetime
+        .to_offset(time::UtcOffset::UTC)
+        .format(&format)
+        .unwrap_or_default()
+}
+
+fn parse_http_date(date: &str) -> Option<OffsetDateTime> {
+    let format = format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+    PrimitiveDateTime::parse(date.trim(), &format)
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
+
+pub fn etag(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<OffsetDateTime>) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    }
+
+    let if_modified_since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_http_date);
+    match (if_modified_since, last_modified) {
+        (Some(since), Some(modified)) => modified.unix_timestamp() <= since.unix_timestamp(),
+        _ => false,
+    }
+}
+
+/// Responds with `body`, or with 304 Not Modified when the request's
+/// validators show the client already has it.
+pub fn conditional_response<B: AsRef<[u8]> + IntoResponse>(
+    headers: &HeaderMap,
+    content_type: &'static str,
+    body: B,
+    last_modified: Option<OffsetDateTime>,
+) -> Response {
+    let etag = etag(body.as_ref());
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+    response_headers.insert(header::ETAG, etag.parse().unwrap());
+    if let Some(last_modified) = last_modified {
+        response_headers.insert(
+            header::LAST_MODIFIED,
+            http_date(last_modified).parse().unwrap(),
+        );
+    }
+
+    if is_not_modified(headers, &etag, last_modified) {
+        response_headers.remove(header::CONTENT_TYPE);
+        (StatusCode::NOT_MODIFIED, response_headers).into_response()
+    } else {
+        (StatusCode::OK, response_headers, body).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_conditional_response() {
+        let modified = datetime!(2024-02-13 10:30:00 UTC);
+        let body = String::from("<rss/>");
+        let tag = etag(body.as_bytes());
+        assert_eq!(http_date(modified), "Tue, 13 Feb 2024 10:30:00 GMT");
+
+        let status = |headers: HeaderMap| {
+            conditional_response(&headers, "application/xml", body.clone(), Some(modified)).status()
+        };
+
+        assert_eq!(status(HeaderMap::new()), StatusCode::OK);
+
+        let mut matching = HeaderMap::new();
+        matching.insert(header::IF_NONE_MATCH, tag.parse().unwrap());
+        assert_eq!(status(matching), StatusCode::NOT_MODIFIED);
+
+        let mut stale = HeaderMap::new();
+        stale.insert(header::IF_NONE_MATCH, "\"other\"".parse().unwrap());
+        stale.insert(
+            header::IF_MODIFIED_SINCE,
+            http_date(modified).parse().unwrap(),
+        );
+        assert_eq!(status(stale), StatusCode::OK);
+
+        let mut since = HeaderMap::new();
+        since.insert(
+            header::IF_MODIFIED_SINCE,
+            http_date(modified).parse().unwrap(),
+        );
+        assert_eq!(status(since), StatusCode::NOT_MODIFIED);
+
+        let mut earlier = HeaderMap::new();
+        earlier.insert(
+            header::IF_MODIFIED_SINCE,
+            "Mon, 12 Feb 2024 10:30:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(status(earlier), StatusCode::OK);
+    }
+}
@@ -0,0 +1,264 @@
+use std::env;
+use std::time::Duration;
+
+use reqwest::{StatusCode, Url};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::crud::blog::Blog;
+use crate::crud::indexnow::{get_unsubmitted, record_submission};
+use crate::utils::config::SiteConfig;
+
+pub const DEFAULT_INDEXNOW_ENDPOINT: &str = "https://api.indexnow.org/indexnow";
+/// Protocol limit of URLs in a single submission.
+const MAX_BATCH: usize = 10_000;
+const MAX_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Outcome of submitting one batch of URLs.
+#[derive(Debug)]
+pub struct Submission {
+    pub urls: Vec<String>,
+    pub status: Option<u16>,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+impl Submission {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Client for an [IndexNow](https://www.indexnow.org/documentation) endpoint.
+pub struct IndexNow {
+    client: reqwest::Client,
+    endpoint: String,
+    key: String,
+    site_url: String,
+    batch_size: usize,
+    backoff: Duration,
+}
+
+impl IndexNow {
+    pub fn new(endpoint: &str, key: &str, site_url: &str) -> IndexNow {
+        IndexNow {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.to_string(),
+            key: key.to_string(),
+            site_url: site_url.trim_end_matches('/').to_string(),
+            batch_size: MAX_BATCH,
+            backoff: INITIAL_BACKOFF,
+        }
+    }
+
+    /// Where search engines verify the key, served by the router.
+    pub fn key_location(&self) -> String {
+        format!("{}/{}.txt", self.site_url, self.key)
+    }
+
+    /// Submits `urls` in batches, retrying each batch with exponential
+    /// backoff on rate limiting, server errors and network failures.
+    pub async fn submit(&self, urls: &[String]) -> Vec<Submission> {
+        let mut submissions = Vec::new();
+        for batch in urls.chunks(self.batch_size) {
+            submissions.push(self.submit_batch(batch).await);
+        }
+        submissions
+    }
+
+    async fn submit_batch(&self, urls: &[String]) -> Submission {
+        let host = Url::parse(&self.site_url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+        let body = json!({
+            "host": host,
+            "key": self.key,
+            "keyLocation": self.key_location(),
+            "urlList": urls,
+        });
+
+        let mut submission = Submission {
+            urls: urls.to_vec(),
+            status: None,
+            attempts: 0,
+            error: None,
+        };
+        let mut backoff = self.backoff;
+
+        loop {
+            submission.attempts += 1;
+            let retry = match self.client.post(&self.endpoint).json(&body).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    submission.status = Some(status.as_u16());
+                    if status.is_success() {
+                        submission.error = None;
+                        return submission;
+                    }
+                    submission.error = Some(format!("endpoint responded with {}", status));
+                    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                }
+                Err(err) => {
+                    submission.status = None;
+                    submission.error = Some(err.to_string());
+                    true
+                }
+            };
+
+            if !retry || submission.attempts >= MAX_ATTEMPTS {
+                return submission;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+/// Keys are 8 to 128 characters. Only lowercase ones are accepted, as the
+/// key file is served from a path and paths are lowercased.
+pub fn is_valid_key(key: &str) -> bool {
+    (8..=128).contains(&key.len())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// The key from `INDEXNOW_KEY`. IndexNow is disabled when it isn't set.
+pub fn indexnow_key() -> Option<String> {
+    let key = env::var("INDEXNOW_KEY").ok()?;
+    if is_valid_key(&key) {
+        Some(key)
+    } else {
+        eprintln!(
+            "INDEXNOW_KEY must be 8 to 128 lowercase letters, digits or dashes, IndexNow disabled"
+        );
+        None
+    }
+}
+
+/// Submits to `INDEXNOW_ENDPOINT`, or the shared endpoint by default, unless
+/// the feature is turned off.
+pub fn indexnow_from_env(site: &SiteConfig) -> Option<IndexNow> {
+    if !site.features.indexnow {
+        return None;
+    }
+    let key = indexnow_key()?;
+    let endpoint =
+        env::var("INDEXNOW_ENDPOINT").unwrap_or_else(|_| DEFAULT_INDEXNOW_ENDPOINT.to_string());
+    Some(IndexNow::new(&endpoint, &key, &site.base_url))
+}
+
+/// Submits `urls` and logs every batch in `indexnow_submission`.
+pub async fn submit_and_record(indexnow: &IndexNow, pool: &PgPool, urls: &[String]) {
+    for submission in indexnow.submit(urls).await {
+        if submission.is_success() {
+            println!("Submitted {} URLs to IndexNow", submission.urls.len());
+        } else {
+            eprintln!(
+                "Error submitting {} URLs to IndexNow after {} attempts: {}",
+                submission.urls.len(),
+                submission.attempts,
+                submission.error.as_deref().unwrap_or_default()
+            );
+        }
+        if let Err(err) = record_submission(pool, &submission).await {
+            eprintln!("Error recording IndexNow submission: {}", err);
+        }
+    }
+}
+
+/// Submits published posts that were added, changed or went live since
+/// they were last submitted successfully.
+pub async fn submit_due(pool: &PgPool, site: &SiteConfig) {
+    let Some(indexnow) = indexnow_from_env(site) else {
+        return;
+    };
+    let blogs = match Blog::get_published_blogs(pool, None, None).await {
+        Ok(blogs) => blogs,
+        Err(err) => {
+            eprintln!("Error fetching published blogs for IndexNow: {}", err);
+            return;
+        }
+    };
+    let urls: Vec<String> = blogs.iter().map(|blog| site.post_url(blog.id)).collect();
+    let modified: Vec<_> = blogs.iter().map(Blog::modified_at).collect();
+
+    match get_unsubmitted(pool, &urls, &modified).await {
+        Ok(due) if due.is_empty() => {}
+        Ok(due) => submit_and_record(&indexnow, pool, &due).await,
+        Err(err) => eprintln!("Error fetching unsubmitted IndexNow URLs: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use axum::extract::State;
+    use axum::{http::StatusCode, routing::post, Json, Router};
+    use serde_json::Value;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_submit() {
+        // Rate limits every other request, rejects batches with a bad URL
+        async fn indexnow(
+            State(requests): State<Arc<AtomicUsize>>,
+            Json(body): Json<Value>,
+        ) -> StatusCode {
+            assert_eq!(body["host"], "www.example.com");
+            assert_eq!(body["keyLocation"], "https://www.example.com/0123abcd.txt");
+            let urls = body["urlList"].as_array().unwrap();
+            if urls.iter().any(|url| url == "invalid") {
+                return StatusCode::UNPROCESSABLE_ENTITY;
+            }
+            match requests.fetch_add(1, Ordering::SeqCst) % 2 {
+                0 => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::ACCEPTED,
+            }
+        }
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/indexnow", post(indexnow))
+            .with_state(requests.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let indexnow = IndexNow {
+            batch_size: 2,
+            backoff: Duration::from_millis(1),
+            ..IndexNow::new(
+                &format!("http://{}/indexnow", address),
+                "0123abcd",
+                "https://www.example.com/",
+            )
+        };
+        let urls: Vec<String> = ["/blog/1", "/blog/2", "/blog/3"]
+            .iter()
+            .map(|path| format!("https://www.example.com{}", path))
+            .collect();
+
+        let submissions = indexnow.submit(&urls).await;
+        assert_eq!(submissions.len(), 2);
+        assert!(submissions.iter().all(Submission::is_success));
+        assert_eq!(submissions[0].urls.len(), 2);
+        assert_eq!(submissions[1].attempts, 2);
+        assert_eq!(submissions[1].status, Some(202));
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        let rejected = indexnow.submit(&[String::from("invalid")]).await;
+        assert!(!rejected[0].is_success());
+        assert_eq!(rejected[0].attempts, 1);
+        assert_eq!(rejected[0].status, Some(422));
+
+        assert!(is_valid_key("0123abcd"));
+        assert!(!is_valid_key("0123ABCD"));
+        assert!(!is_valid_key("short"));
+    }
+}
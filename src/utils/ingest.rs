@@ -1,10 +1,18 @@
+use std::collections::HashMap;
 use std::fs;
 
-use crate::{crud::blog::Blog, utils::db::get_db};
+use crate::{
+    crud::blog::{Blog, RELATED_POSTS},
+    crud::newsletter::Newsletter,
+    utils::db::get_db,
+    utils::embedding::{chunk_text, embedder_from_env, Embedder},
+};
 use regex::Regex;
-use time::{macros::format_description, Date};
+use time::{macros::format_description, Date, OffsetDateTime};
 
 pub async fn ingest_articles() -> Option<()> {
+    let mut related_pins: HashMap<i32, Vec<i32>> = HashMap::new();
+
     match fs::read_dir("./articles") {
         Ok(files) => {
             for file in files {
@@ -14,15 +22,57 @@ pub async fn ingest_articles() -> Option<()> {
                     .expect(format!("Error reading from {:?}", &path).as_str());
 
                 if let Some(blog_id) = get_id(content.as_str()).await {
-                    if !blog_exists(&blog_id).await {
-                        if let Some(metadata) = get_metadata(content.as_str()) {
+                    let metadata = get_metadata(content.as_str());
+
+                    if let Some(pins) = metadata.as_ref().and_then(Metadata::related_ids) {
+                        related_pins.insert(blog_id, pins);
+                    }
+
+                    if blog_exists(&blog_id).await {
+                        if let Some(metadata) = metadata {
+                            if let Some(blog) = metadata_to_blog(metadata).await {
+                                let pool = get_db().await;
+                                match blog.update_blog(&pool).await {
+                                    Ok(true) => {
+                                        // Embedded again further down
+                                        if let Err(err) = Blog::delete_chunks(&pool, blog.id).await
+                                        {
+                                            eprintln!(
+                                                "Error deleting chunks of blog {}: {}",
+                                                blog.id, err
+                                            );
+                                        }
+                                    }
+                                    Ok(false) => {}
+                                    Err(err) => {
+                                        eprintln!("Error updating blog {}: {}", blog_id, err)
+                                    }
+                                }
+                            }
+                        }
+                    } else {
+                        if let Some(metadata) = metadata {
                             let blog = metadata_to_blog(metadata).await.unwrap();
                             let pool = get_db().await;
 
-                            blog.create_blog(&pool).await.unwrap_or_else(|err| {
-                                eprintln!("Error inserting blog: {}", err);
-                            });
-                            println!("Added blog {}", blog.id);
+                            match blog.create_blog(&pool).await {
+                                Ok(_) => {
+                                    println!("Added blog {}", blog.id);
+                                    // Sent once the post's date arrives. Back-dated
+                                    // posts, or a restored database, aren't news
+                                    let today = OffsetDateTime::now_utc().date();
+                                    if blog.date >= today {
+                                        if let Err(err) = Newsletter::enqueue(&pool, blog.id).await
+                                        {
+                                            eprintln!(
+                                                "Error queueing newsletter for blog {}: {}",
+                                                blog.id, err
+                                            );
+                                        }
+                                    }
+                                }
+                                Err(err) => eprintln!("Error inserting blog: {}", err),
+                            }
                         } else {
                             eprintln!("No metadata found, skipping blog {}", blog_id)
                         }
@@ -32,9 +82,58 @@ pub async fn ingest_articles() -> Option<()> {
         }
         Err(e) => eprintln!("Error reading from dir `articles`: {}", e),
     }
+
+    ingest_related(&related_pins).await;
+
+    if let Some(embedder) = embedder_from_env() {
+        ingest_embeddings(&embedder).await;
+    }
+
     Some(())
 }
 
+async fn ingest_related(pins: &HashMap<i32, Vec<i32>>) {
+    let pool = get_db().await;
+    match Blog::get_all_blogs(&pool).await {
+        Ok(blogs) => {
+            let related = Blog::compute_related(&blogs, pins, RELATED_POSTS);
+            match Blog::store_related(&pool, &related).await {
+                Ok(_) => println!("Stored {} related posts", related.len()),
+                Err(err) => eprintln!("Error storing related posts: {}", err),
+            }
+        }
+        Err(err) => eprintln!("Error fetching blogs for related posts: {}", err),
+    }
+}
+
+async fn ingest_embeddings(embedder: &impl Embedder) {
+    let pool = get_db().await;
+    let blogs = match Blog::get_blogs_without_chunks(&pool).await {
+        Ok(blogs) => blogs,
+        Err(err) => {
+            eprintln!("Error fetching blogs for embedding: {}", err);
+            return;
+        }
+    };
+
+    for blog in blogs {
+        let chunks: Vec<String> = chunk_text(&blog.body)
+            .into_iter()
+            .map(|chunk| format!("{}\n\n{}", blog.title, chunk))
+            .collect();
+
+        match embedder.embed(&chunks).await {
+            Ok(embeddings) => {
+                match Blog::store_chunks(&pool, blog.id, &chunks, &embeddings).await {
+                    Ok(_) => println!("Embedded {} chunks of blog {}", chunks.len(), blog.id),
+                    Err(err) => eprintln!("Error storing chunks of blog {}: {}", blog.id, err),
+                }
+            }
+            Err(err) => eprintln!("Error embedding blog {}: {}", blog.id, err),
+        }
+    }
+}
+
 async fn get_id(text: &str) -> Option<i32> {
     let id_re = Regex::new(r"% id: (.+)").unwrap();
     for line in text.lines() {
@@ -50,16 +149,15 @@ async fn get_id(text: &str) -> Option<i32> {
 
 async fn blog_exists(id: &i32) -> bool {
     let pool = get_db().await;
-    match Blog::get_blog(&pool, *id).await {
-        Ok(_) => return true,
-        _ => return false,
-    }
+    Blog::blog_exists(&pool, *id).await.unwrap_or(false)
 }
 
 async fn metadata_to_blog(metadata: Metadata) -> Option<Blog> {
     // let settings = get_settings(None);
 
     if metadata.is_complete() {
+        let pinned = metadata.is_pinned();
+        let featured = metadata.is_featured();
         let id = metadata.id.unwrap().parse::<i32>().ok()?;
         let title = metadata.title.clone().unwrap();
         let body = metadata.body.clone().unwrap();
@@ -83,6 +181,9 @@ async fn metadata_to_blog(metadata: Metadata) -> Option<Blog> {
             body,
             date,
             tags,
+            pinned,
+            featured,
+            updated_at: OffsetDateTime::now_utc(),
         };
 
         Some(blog)
@@ -98,12 +199,33 @@ struct Metadata {
     body: Option<String>,
     date: Option<String>,
     tags: Option<String>,
+    related: Option<String>,
+    pinned: Option<String>,
+    featured: Option<String>,
 }
 
 impl Metadata {
     fn is_complete(&self) -> bool {
         self.id.is_some() && self.title.is_some() && self.date.is_some() && self.tags.is_some()
     }
+
+    fn is_pinned(&self) -> bool {
+        self.pinned.as_deref() == Some("true")
+    }
+
+    fn is_featured(&self) -> bool {
+        self.featured.as_deref() == Some("true")
+    }
+
+    fn related_ids(&self) -> Option<Vec<i32>> {
+        let ids: Vec<i32> = self
+            .related
+            .as_ref()?
+            .split(',')
+            .filter_map(|id| id.trim().parse::<i32>().ok())
+            .collect();
+        Some(ids)
+    }
 }
 
 fn get_metadata(text: &str) -> Option<Metadata> {
@@ -113,6 +235,9 @@ fn get_metadata(text: &str) -> Option<Metadata> {
         body: None,
         date: None,
         tags: None,
+        related: None,
+        pinned: None,
+        featured: None,
     };
 
     let metadata_re = Regex::new(r"% (\w+): (.+)").unwrap();
@@ -129,6 +254,9 @@ fn get_metadata(text: &str) -> Option<Metadata> {
                     "title" => metadata.title = Some(value.as_str().to_string()),
                     "date" => metadata.date = Some(value.as_str().to_string()),
                     "tags" => metadata.tags = Some(value.as_str().to_string()),
+                    "related" => metadata.related = Some(value.as_str().to_string()),
+                    "pinned" => metadata.pinned = Some(value.as_str().to_string()),
+                    "featured" => metadata.featured = Some(value.as_str().to_string()),
                     _ => {}
                 }
             }
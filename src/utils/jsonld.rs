@@ -0,0 +1,321 @@
+use serde::Serialize;
+
+use crate::utils::config::SiteConfig;
+
+const CONTEXT: &str = "https://schema.org";
+
+/// A schema.org document with its `@context`.
+#[derive(Serialize)]
+struct Document<'a, T: Serialize> {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    #[serde(flatten)]
+    item: &'a T,
+}
+
+/// Serializes `item` for an `application/ld+json` script. `<`, `>` and `&`
+/// are escaped so a value can't close the script element, which keeps the
+/// output safe to render with `|safe`.
+pub fn to_script<T: Serialize>(item: &T) -> String {
+    let document = Document {
+        context: CONTEXT,
+        item,
+    };
+    match serde_json::to_string(&document) {
+        Ok(json) => json
+            .replace('<', "\\u003c")
+            .replace('>', "\\u003e")
+            .replace('&', "\\u0026")
+            .replace('\u{2028}', "\\u2028")
+            .replace('\u{2029}', "\\u2029"),
+        Err(err) => {
+            eprintln!("Failed to serialize structured data: {}", err);
+            String::from("{}")
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub same_as: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub works_for: Option<Organization>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alumni_of: Vec<Organization>,
+}
+
+impl Person {
+    pub fn new(name: &str, url: &str) -> Person {
+        Person {
+            kind: "Person",
+            name: name.to_string(),
+            url: url.to_string(),
+            same_as: vec![],
+            job_title: None,
+            description: None,
+            works_for: None,
+            alumni_of: vec![],
+        }
+    }
+
+    /// The author of the site.
+    pub fn author(site: &SiteConfig) -> Person {
+        Person::new(&site.author, &site.base_url)
+    }
+
+    /// The author with their profiles, for the pages about them.
+    pub fn profile(site: &SiteConfig) -> Person {
+        Person {
+            same_as: site.social.same_as(),
+            job_title: Some(site.job_title.clone()),
+            description: Some(site.description.clone()),
+            ..Person::author(site)
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Organization {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Organization {
+    pub fn new(name: &str, url: Option<&str>) -> Organization {
+        Organization {
+            kind: "Organization",
+            name: name.to_string(),
+            url: url.map(String::from),
+        }
+    }
+
+    pub fn school(name: &str, url: &str) -> Organization {
+        Organization {
+            kind: "EducationalOrganization",
+            ..Organization::new(name, Some(url))
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct WebSite {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub name: String,
+    pub url: String,
+    pub description: String,
+    pub author: Person,
+}
+
+impl WebSite {
+    pub fn new(site: &SiteConfig) -> WebSite {
+        WebSite {
+            kind: "WebSite",
+            name: site.name.clone(),
+            url: site.base_url.clone(),
+            description: site.description.clone(),
+            author: Person::author(site),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct WebPage {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub name: String,
+    pub description: String,
+    pub url: String,
+    pub author: Person,
+    pub publisher: Person,
+}
+
+impl WebPage {
+    pub fn new(site: &SiteConfig, name: &str, description: &str, url: &str) -> WebPage {
+        WebPage {
+            kind: "WebPage",
+            name: name.to_string(),
+            description: description.to_string(),
+            url: url.to_string(),
+            author: Person::author(site),
+            publisher: Person::author(site),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct WebPageRef {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    #[serde(rename = "@id")]
+    id: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlogPosting {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub headline: String,
+    pub description: String,
+    pub url: String,
+    pub image: String,
+    pub date_published: String,
+    pub date_modified: String,
+    pub author: Person,
+    pub publisher: Person,
+    main_entity_of_page: WebPageRef,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<String>,
+}
+
+impl BlogPosting {
+    pub fn new(
+        site: &SiteConfig,
+        id: i32,
+        headline: &str,
+        description: &str,
+        date_published: String,
+        date_modified: String,
+        tags: &[String],
+    ) -> BlogPosting {
+        let url = site.post_url(id);
+        BlogPosting {
+            kind: "BlogPosting",
+            headline: headline.to_string(),
+            description: description.to_string(),
+            image: format!("{}/og.png", url),
+            date_published,
+            date_modified,
+            author: Person::author(site),
+            publisher: Person::author(site),
+            main_entity_of_page: WebPageRef {
+                kind: "WebPage",
+                id: url.clone(),
+            },
+            keywords: (!tags.is_empty()).then(|| tags.join(", ")),
+            url,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListItem {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub position: usize,
+    pub name: String,
+    pub item: String,
+}
+
+/// `(name, url)` pairs as list items, numbered from 1.
+fn list_items(items: Vec<(String, String)>) -> Vec<ListItem> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, item))| ListItem {
+            kind: "ListItem",
+            position: i + 1,
+            name,
+            item,
+        })
+        .collect()
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BreadcrumbList {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub item_list_element: Vec<ListItem>,
+}
+
+impl BreadcrumbList {
+    pub fn new(crumbs: Vec<(String, String)>) -> BreadcrumbList {
+        BreadcrumbList {
+            kind: "BreadcrumbList",
+            item_list_element: list_items(crumbs),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemList {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    pub item_list_element: Vec<ListItem>,
+}
+
+impl ItemList {
+    pub fn new(items: Vec<(String, String)>) -> ItemList {
+        ItemList {
+            kind: "ItemList",
+            item_list_element: list_items(items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::utils::config::test_site;
+
+    use super::*;
+
+    #[test]
+    fn test_to_script() {
+        let posting = BlogPosting::new(
+            &test_site(),
+            4,
+            "Quotes \" and </script><script>alert(1)</script>",
+            "R&D",
+            String::from("2024-02-13"),
+            String::from("2024-03-01T12:00:00Z"),
+            &[String::from("web"), String::from("rust")],
+        );
+
+        let script = to_script(&posting);
+        assert!(!script.contains('<'));
+        assert!(!script.contains('&'));
+
+        let value: Value = serde_json::from_str(&script).unwrap();
+        assert_eq!(value["@context"], "https://schema.org");
+        assert_eq!(value["@type"], "BlogPosting");
+        assert_eq!(
+            value["headline"],
+            "Quotes \" and </script><script>alert(1)</script>"
+        );
+        assert_eq!(value["author"]["@type"], "Person");
+        assert_eq!(
+            value["mainEntityOfPage"]["@id"],
+            "https://www.example.com/blog/4"
+        );
+        assert_eq!(value["keywords"], "web, rust");
+    }
+
+    #[test]
+    fn test_list_items() {
+        let list = ItemList::new(vec![
+            (String::from("First"), test_site().post_url(1)),
+            (String::from("Second"), test_site().post_url(2)),
+        ]);
+        let value: Value = serde_json::from_str(&to_script(&list)).unwrap();
+        assert_eq!(value["itemListElement"][1]["position"], 2);
+        assert_eq!(value["itemListElement"][1]["@type"], "ListItem");
+    }
+}
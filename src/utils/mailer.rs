@@ -0,0 +1,335 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use time::OffsetDateTime;
+
+use crate::utils::config::SiteConfig;
+use crate::utils::robots::{is_lambda, is_production};
+
+const DEFAULT_SMTP_PORT: u16 = 587;
+const DEFAULT_MAIL_DIR: &str = "mail";
+
+/// An email with an HTML body and its plain text alternative. Every email
+/// carries a link to unsubscribe, also offered to mail clients through the
+/// `List-Unsubscribe` headers of RFC 8058.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    pub unsubscribe_url: String,
+}
+
+impl Email {
+    fn message(&self, from: &Mailbox) -> Result<Message, String> {
+        let to = self
+            .to
+            .parse::<Mailbox>()
+            .map_err(|err| format!("invalid recipient {}: {}", self.to, err))?;
+        Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(&self.subject)
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                format!("<{}>", self.unsubscribe_url),
+            ))
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                String::from("List-Unsubscribe=One-Click"),
+            ))
+            .multipart(MultiPart::alternative_plain_html(
+                self.text.clone(),
+                self.html.clone(),
+            ))
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+/// Delivers through an SMTP server. Without TLS it talks plain SMTP, for
+/// local sinks like Mailpit.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        tls: bool,
+        from: Mailbox,
+    ) -> Result<SmtpMailer, String> {
+        let builder = if tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|err| err.to_string())?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        let builder = match credentials {
+            Some((username, password)) => builder.credentials(Credentials::new(username, password)),
+            None => builder,
+        };
+        Ok(SmtpMailer {
+            transport: builder.port(port).build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let message = email.message(&self.from)?;
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Writes every email to an `.eml` file instead of sending it, for
+/// development.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: Mailbox) -> FileMailer {
+        FileMailer {
+            dir: dir.into(),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let message = email.message(&self.from)?;
+        let recipient: String = email
+            .to
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            OffsetDateTime::now_utc().unix_timestamp_nanos(),
+            recipient
+        ));
+
+        std::fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        std::fs::write(&path, message.formatted()).map_err(|err| err.to_string())?;
+        println!("Wrote email to {} at {}", email.to, path.display());
+        Ok(())
+    }
+}
+
+/// `MAIL_FROM`, or the site's name on `newsletter@` its domain.
+fn sender(site: &SiteConfig) -> Mailbox {
+    let default = || {
+        let domain = site.domain().trim_start_matches("www.");
+        format!("{} <newsletter@{}>", site.name, domain)
+    };
+    let from = env::var("MAIL_FROM").unwrap_or_else(|_| default());
+    from.parse().unwrap_or_else(|err| {
+        eprintln!(
+            "MAIL_FROM isn't a valid address ({}), using the default",
+            err
+        );
+        default().parse().expect("the default sender must be valid")
+    })
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Sends through `SMTP_HOST` when it's set, with `SMTP_PORT` (default 587),
+/// `SMTP_USERNAME`, `SMTP_PASSWORD` and `SMTP_TLS` (default true). Otherwise
+/// emails are written to `MAIL_DIR` (default `mail`), except in production
+/// and on Lambda, with its read-only filesystem, where emails are turned off
+/// and this returns `None`.
+pub fn mailer_from_env(site: &SiteConfig) -> Option<Arc<dyn Mailer>> {
+    let from = sender(site);
+    let mail_dir = env::var("MAIL_DIR").unwrap_or_else(|_| DEFAULT_MAIL_DIR.to_string());
+    let deployed = is_production() || is_lambda();
+
+    let Some(host) = non_empty_var("SMTP_HOST") else {
+        if deployed {
+            eprintln!("SMTP_HOST isn't set, emails are turned off");
+            return None;
+        }
+        println!("SMTP_HOST isn't set, writing emails to {}", mail_dir);
+        return Some(Arc::new(FileMailer::new(mail_dir, from)));
+    };
+    let port = env::var("SMTP_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_SMTP_PORT);
+    let credentials = match (
+        non_empty_var("SMTP_USERNAME"),
+        non_empty_var("SMTP_PASSWORD"),
+    ) {
+        (Some(username), Some(password)) => Some((username, password)),
+        _ => None,
+    };
+    let tls = env::var("SMTP_TLS").map_or(true, |tls| tls != "false");
+
+    match SmtpMailer::new(&host, port, credentials, tls, from.clone()) {
+        Ok(mailer) => Some(Arc::new(mailer)),
+        Err(err) if deployed => {
+            eprintln!("Error setting up SMTP, emails are turned off: {}", err);
+            None
+        }
+        Err(err) => {
+            eprintln!(
+                "Error setting up SMTP, writing emails to {}: {}",
+                mail_dir, err
+            );
+            Some(Arc::new(FileMailer::new(mail_dir, from)))
+        }
+    }
+}
+
+/// Keeps sent emails in memory, for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryMailer {
+    pub sent: std::sync::Mutex<Vec<Email>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+/// Refuses one address, like a server rejecting a recipient, for tests.
+#[cfg(test)]
+pub struct FlakyMailer {
+    pub inner: MemoryMailer,
+    pub refuse: &'static str,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Mailer for FlakyMailer {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        if email.to == self.refuse {
+            return Err(String::from("550 mailbox unavailable"));
+        }
+        self.inner.send(email).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+
+    fn email() -> Email {
+        Email {
+            to: String::from("reader@example.com"),
+            subject: String::from("Confirm your subscription"),
+            text: String::from("Follow the link"),
+            html: String::from("<p>Follow the link</p>"),
+            unsubscribe_url: String::from("https://www.example.com/unsubscribe/abc"),
+        }
+    }
+
+    fn from() -> Mailbox {
+        "Example <newsletter@example.com>".parse().unwrap()
+    }
+
+    /// Accepts a single message and returns what was sent as DATA.
+    async fn smtp_sink(listener: tokio::net::TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let command = line.to_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250 sink\r\n"
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if command.starts_with("QUIT") {
+                writer.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_smtp_mailer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sink = tokio::spawn(smtp_sink(listener));
+
+        let mailer = SmtpMailer::new("127.0.0.1", port, None, false, from()).unwrap();
+        mailer.send(&email()).await.unwrap();
+        drop(mailer);
+
+        let data = sink.await.unwrap();
+        assert!(data.contains("To: reader@example.com"));
+        assert!(data.contains("Subject: Confirm your subscription"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("List-Unsubscribe: <https://www.example.com/unsubscribe/abc>"));
+        assert!(data.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+    }
+
+    #[tokio::test]
+    async fn test_file_mailer() {
+        let dir = env::temp_dir().join(format!(
+            "webrs-mail-{}",
+            OffsetDateTime::now_utc().unix_timestamp_nanos()
+        ));
+        FileMailer::new(&dir, from()).send(&email()).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let path = files[0].as_ref().unwrap().path();
+        let eml = std::fs::read_to_string(&path).unwrap();
+        assert!(eml.contains("From: Example <newsletter@example.com>"));
+        assert!(eml.contains("Follow the link"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,22 @@
+pub mod canonical;
+pub mod config;
 pub mod db;
+pub mod email;
+pub mod embedding;
+pub mod feed;
 pub mod html;
+pub mod http;
+pub mod indexnow;
 pub mod ingest;
+pub mod jsonld;
+pub mod mailer;
+pub mod newsletter;
+pub mod og;
+pub mod pagination;
+pub mod robots;
+pub mod similarity;
+pub mod sitemap;
+pub mod suggest;
+pub mod tasks;
+pub mod token;
+pub mod webmention;
@@ -0,0 +1,314 @@
+use askama::Template;
+use sqlx::error::Error;
+use sqlx::PgPool;
+
+use crate::crud::blog::Blog;
+use crate::crud::newsletter::Newsletter;
+use crate::utils::config::{AppState, SiteConfig};
+use crate::utils::mailer::{Email, Mailer};
+use crate::utils::token::UnsubscribeKey;
+
+/// Deliveries per scheduled run, so a run ends well within its timeout
+/// rather than being cut off mid-send.
+const DELIVERIES_PER_RUN: u32 = 100;
+
+#[derive(Template)]
+#[template(path = "emails/newsletter.html")]
+struct NewsletterHtml<'a> {
+    site: &'a SiteConfig,
+    title: &'a str,
+    summary: &'a str,
+    url: &'a str,
+    unsubscribe_url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/newsletter.txt")]
+struct NewsletterText<'a> {
+    site: &'a SiteConfig,
+    title: &'a str,
+    summary: &'a str,
+    url: &'a str,
+    unsubscribe_url: &'a str,
+}
+
+/// What a run of [`deliver`] did.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub added: u64,
+    pub sent: u32,
+    pub failed: u32,
+}
+
+fn newsletter_email(
+    site: &SiteConfig,
+    unsubscribe: &UnsubscribeKey,
+    blog: &Blog,
+    to: &str,
+) -> Result<Email, String> {
+    let url = site.post_url(blog.id);
+    let unsubscribe_url = unsubscribe.url(site, to);
+    let html = NewsletterHtml {
+        site,
+        title: &blog.title,
+        summary: &blog.summary,
+        url: &url,
+        unsubscribe_url: &unsubscribe_url,
+    };
+    let text = NewsletterText {
+        site,
+        title: &blog.title,
+        summary: &blog.summary,
+        url: &url,
+        unsubscribe_url: &unsubscribe_url,
+    };
+    Ok(Email {
+        to: to.to_string(),
+        subject: blog.title.clone(),
+        text: text.render().map_err(|err| err.to_string())?,
+        html: html.render().map_err(|err| err.to_string())?,
+        unsubscribe_url,
+    })
+}
+
+/// Sends the newsletter for a post to every confirmed subscriber that didn't
+/// get it yet, or to at most `limit` of them. Each delivery is claimed before
+/// sending, so a run that's interrupted can be started again without sending
+/// anything twice.
+pub async fn deliver(
+    pool: &PgPool,
+    site: &SiteConfig,
+    mailer: &dyn Mailer,
+    unsubscribe: &UnsubscribeKey,
+    blog_id: i32,
+    retry_failed: bool,
+    limit: Option<u32>,
+) -> Result<Report, Error> {
+    let blog = Blog::get_blog(pool, blog_id).await?;
+    Newsletter::enqueue(pool, blog_id).await?;
+    let mut report = Report {
+        added: Newsletter::add_deliveries(pool, blog_id).await?,
+        ..Report::default()
+    };
+    if retry_failed {
+        Newsletter::retry_failed(pool, blog_id).await?;
+    }
+
+    while limit.is_none_or(|limit| report.sent + report.failed < limit) {
+        let Some(delivery) = Newsletter::claim_next(pool, blog_id).await? else {
+            break;
+        };
+        let sent = match newsletter_email(site, unsubscribe, &blog, &delivery.email) {
+            Ok(email) => mailer.send(&email).await,
+            Err(err) => Err(err),
+        };
+        match sent {
+            Ok(()) => {
+                Newsletter::mark_sent(pool, delivery.id).await?;
+                report.sent += 1;
+            }
+            Err(err) => {
+                eprintln!(
+                    "Error sending blog {} to {}: {}",
+                    blog_id, delivery.email, err
+                );
+                Newsletter::mark_failed(pool, delivery.id, &err).await?;
+                report.failed += 1;
+            }
+        }
+    }
+
+    Newsletter::complete(pool, blog_id).await?;
+    Ok(report)
+}
+
+/// Delivers the newsletters of posts that have been published since they
+/// were queued, up to `DELIVERIES_PER_RUN`. The rest go out on the next runs.
+pub async fn send_due(
+    pool: &PgPool,
+    site: &SiteConfig,
+    mailer: &dyn Mailer,
+    unsubscribe: &UnsubscribeKey,
+) {
+    let due = match Newsletter::get_due(pool).await {
+        Ok(due) => due,
+        Err(err) => {
+            eprintln!("Error fetching due newsletters: {}", err);
+            return;
+        }
+    };
+
+    let mut remaining = DELIVERIES_PER_RUN;
+    for blog_id in due {
+        if remaining == 0 {
+            break;
+        }
+        match deliver(
+            pool,
+            site,
+            mailer,
+            unsubscribe,
+            blog_id,
+            false,
+            Some(remaining),
+        )
+        .await
+        {
+            Ok(report) => {
+                remaining -= report.sent + report.failed;
+                println!(
+                    "Sent newsletter for blog {} to {} subscribers, {} failed",
+                    blog_id, report.sent, report.failed
+                )
+            }
+            Err(err) => eprintln!("Error sending newsletter for blog {}: {}", blog_id, err),
+        }
+    }
+}
+
+/// The post id of `newsletter send --post <id>`.
+pub fn parse_command(args: &[String]) -> Option<i32> {
+    match args {
+        [newsletter, send, post, id]
+            if newsletter == "newsletter" && send == "send" && post == "--post" =>
+        {
+            id.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Sends a post's newsletter by hand, including its failed deliveries.
+pub async fn send_post(state: &AppState, blog_id: i32) -> Result<(), String> {
+    let mailer = state
+        .mailer
+        .as_ref()
+        .ok_or("Emails are turned off, set SMTP_HOST to send newsletters")?;
+    let report = deliver(
+        &state.pool,
+        &state.site,
+        mailer.as_ref(),
+        &state.unsubscribe,
+        blog_id,
+        true,
+        None,
+    )
+    .await
+    .map_err(|err| format!("Error sending newsletter for blog {}: {}", blog_id, err))?;
+
+    println!(
+        "Sent newsletter for blog {} to {} subscribers ({} new), {} failed",
+        blog_id, report.sent, report.added, report.failed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::time::OffsetDateTime;
+
+    use crate::crud::blog::test_blog;
+    use crate::crud::subscriber::Subscriber;
+    use crate::utils::config::test_site;
+    use crate::utils::db::get_db;
+    use crate::utils::mailer::{FlakyMailer, MemoryMailer};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let args = |args: &str| args.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(parse_command(&args("newsletter send --post 12")), Some(12));
+        assert_eq!(parse_command(&args("newsletter send --post twelve")), None);
+        assert_eq!(parse_command(&args("newsletter send 12")), None);
+        assert_eq!(parse_command(&args("serve")), None);
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_deliver() {
+        let pool = get_db().await;
+        let site = test_site();
+        let unsubscribe = UnsubscribeKey::new(b"secret");
+        let blog_id = 4901;
+        let blog = Blog {
+            title: String::from("Newsletter test"),
+            summary: String::from("What's new"),
+            ..test_blog(blog_id)
+        };
+        blog.create_blog(&pool).await.unwrap();
+
+        let emails = ["first@newsletter.test", "second@newsletter.test"];
+        for email in emails {
+            let token = format!("newsletter-{}", email);
+            Subscriber::create_subscriber(&pool, email, &token, OffsetDateTime::now_utc(), "test")
+                .await
+                .unwrap();
+            sqlx::query("UPDATE subscriber SET status = 'confirmed' WHERE email = $1")
+                .bind(email)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        // Count everyone else as sent already, so only the test's own
+        // subscribers are delivered to
+        Newsletter::enqueue(&pool, blog_id).await.unwrap();
+        sqlx::query(
+            "INSERT INTO newsletter_delivery (blog_id, email, status)
+            SELECT $1, email, 'sent' FROM subscriber
+            WHERE status = 'confirmed' AND NOT email = ANY($2)",
+        )
+        .bind(blog_id)
+        .bind(&emails[..])
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let flaky = FlakyMailer {
+            inner: MemoryMailer::default(),
+            refuse: emails[1],
+        };
+        let sent_to = |mailer: &MemoryMailer, email: &str| {
+            let sent = mailer.sent.lock().unwrap();
+            sent.iter()
+                .filter(|sent| sent.to == email)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let report = deliver(&pool, &site, &flaky, &unsubscribe, blog_id, false, None)
+            .await
+            .unwrap();
+        assert_eq!(report.sent, 1);
+        assert_eq!(report.failed, 1);
+        let sent = sent_to(&flaky.inner, emails[0]);
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].text.contains("https://www.example.com/blog/4901"));
+        assert!(sent[0].text.contains(&unsubscribe.url(&site, emails[0])));
+
+        // Running again sends nothing twice, retrying only sends what failed
+        let mailer = MemoryMailer::default();
+        deliver(&pool, &site, &mailer, &unsubscribe, blog_id, false, None)
+            .await
+            .unwrap();
+        assert!(sent_to(&mailer, emails[0]).is_empty());
+        assert!(sent_to(&mailer, emails[1]).is_empty());
+        deliver(&pool, &site, &mailer, &unsubscribe, blog_id, true, Some(0))
+            .await
+            .unwrap();
+        assert!(sent_to(&mailer, emails[1]).is_empty());
+        deliver(&pool, &site, &mailer, &unsubscribe, blog_id, true, None)
+            .await
+            .unwrap();
+        assert!(sent_to(&mailer, emails[0]).is_empty());
+        assert_eq!(sent_to(&mailer, emails[1]).len(), 1);
+
+        sqlx::query("DELETE FROM blog WHERE id = $1")
+            .bind(blog_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        for email in emails {
+            Subscriber::delete_subscriber(&pool, email).await.unwrap();
+        }
+    }
+}
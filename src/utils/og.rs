@@ -0,0 +1,173 @@
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use askama::Template;
+use resvg::{tiny_skia, usvg};
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+
+pub const OG_WIDTH: u32 = 1200;
+pub const OG_HEIGHT: u32 = 630;
+// Characters of the 64px monospace title that fit between the margins
+const TITLE_LINE_CHARS: usize = 27;
+const MAX_TITLE_LINES: usize = 4;
+
+static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+#[derive(Template)]
+#[template(path = "og.svg")]
+struct OgTemplate {
+    title_lines: Vec<String>,
+    date: String,
+    tags: String,
+    site: String,
+}
+
+#[derive(Debug)]
+pub enum OgError {
+    Template(askama::Error),
+    Svg(usvg::Error),
+    Png(String),
+}
+
+impl fmt::Display for OgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OgError::Template(err) => write!(f, "failed to render og template: {}", err),
+            OgError::Svg(err) => write!(f, "failed to parse og svg: {}", err),
+            OgError::Png(msg) => write!(f, "failed to encode og png: {}", msg),
+        }
+    }
+}
+
+/// The fonts are embedded, as the Lambda runtime has no system fonts.
+fn fonts() -> Arc<usvg::fontdb::Database> {
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = usvg::fontdb::Database::new();
+            fonts.load_font_data(include_bytes!("../../assets/fonts/DejaVuSansMono.ttf").to_vec());
+            fonts.load_font_data(
+                include_bytes!("../../assets/fonts/DejaVuSansMono-Bold.ttf").to_vec(),
+            );
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+/// Renders the 1200x630 Open Graph card of a post as PNG.
+pub fn render_og_image(blog: &Blog, site: &SiteConfig) -> Result<Vec<u8>, OgError> {
+    let template = OgTemplate {
+        title_lines: wrap_title(&blog.title),
+        date: blog.date.to_string(),
+        tags: blog.tags.join(" "),
+        site: site.domain().to_string(),
+    };
+    let svg = template.render().map_err(OgError::Template)?;
+
+    let options = usvg::Options {
+        fontdb: fonts(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(&svg, &options).map_err(OgError::Svg)?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(OG_WIDTH, OG_HEIGHT)
+        .ok_or_else(|| OgError::Png(String::from("invalid image size")))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|err| OgError::Png(err.to_string()))
+}
+
+/// Word wraps the title to at most `MAX_TITLE_LINES`, dropping emoji the
+/// embedded font can't draw.
+fn wrap_title(title: &str) -> Vec<String> {
+    let title: String = title.chars().filter(|c| !is_emoji(*c)).collect();
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in title.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > TITLE_LINE_CHARS {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            lines.push(word.drain(..TITLE_LINE_CHARS).collect());
+        }
+        let word: String = word.into_iter().collect();
+
+        if !current.is_empty()
+            && current.chars().count() + 1 + word.chars().count() > TITLE_LINE_CHARS
+        {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.len() > MAX_TITLE_LINES {
+        lines.truncate(MAX_TITLE_LINES);
+        let last: String = lines[MAX_TITLE_LINES - 1]
+            .chars()
+            .take(TITLE_LINE_CHARS - 1)
+            .collect();
+        lines[MAX_TITLE_LINES - 1] = format!("{}…", last.trim_end());
+    }
+    lines
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c, '\u{2600}'..='\u{27BF}' | '\u{FE0F}' | '\u{200D}' | '\u{1F000}'..)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::crud::blog::test_blog;
+    use crate::utils::config::test_site;
+
+    use super::*;
+
+    #[test]
+    fn test_wrap_title() {
+        assert_eq!(
+            wrap_title("WASM with Rust and Javascript 🦀"),
+            vec!["WASM with Rust and", "Javascript"]
+        );
+        let long = wrap_title(&"word ".repeat(40));
+        assert_eq!(long.len(), MAX_TITLE_LINES);
+        assert!(long[MAX_TITLE_LINES - 1].ends_with('…'));
+        assert!(long
+            .iter()
+            .all(|line| line.chars().count() <= TITLE_LINE_CHARS));
+    }
+
+    #[test]
+    fn test_render_og_image() {
+        let blog = Blog {
+            title: String::from("Concurrent data retrieval <in> Rust & Go"),
+            date: date!(2024 - 10 - 25),
+            tags: vec![String::from("async")],
+            ..test_blog(1)
+        };
+
+        let png = render_og_image(&blog, &test_site()).unwrap();
+
+        assert_eq!(&png[1..4], b"PNG");
+        // Width and height in the IHDR chunk
+        assert_eq!(
+            u32::from_be_bytes(png[16..20].try_into().unwrap()),
+            OG_WIDTH
+        );
+        assert_eq!(
+            u32::from_be_bytes(png[20..24].try_into().unwrap()),
+            OG_HEIGHT
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+
+use serde::Deserialize;
+use time::macros::format_description;
+use time::Date;
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 50;
+/// Far beyond the last page, but keeps offsets from overflowing.
+pub const MAX_PAGE: i64 = 1_000_000;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PaginationParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    cursor: Option<String>,
+}
+
+impl PaginationParams {
+    pub fn has_page(&self) -> bool {
+        self.page.is_some()
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>, PaginationError> {
+        self.cursor.as_deref().map(Cursor::decode).transpose()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PaginationError {
+    Page(i64),
+    PerPage(i64),
+    Cursor,
+}
+
+impl fmt::Display for PaginationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaginationError::Page(page) => {
+                write!(f, "page must be between 1 and {}, got {}", MAX_PAGE, page)
+            }
+            PaginationError::PerPage(per_page) => write!(
+                f,
+                "per_page must be between 1 and {}, got {}",
+                MAX_PER_PAGE, per_page
+            ),
+            PaginationError::Cursor => write!(f, "cursor is invalid"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRequest {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl PageRequest {
+    pub fn new(page: i64, per_page: i64) -> Result<PageRequest, PaginationError> {
+        if !(1..=MAX_PAGE).contains(&page) {
+            return Err(PaginationError::Page(page));
+        }
+        if !(1..=MAX_PER_PAGE).contains(&per_page) {
+            return Err(PaginationError::PerPage(per_page));
+        }
+        Ok(PageRequest { page, per_page })
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        PageRequest {
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+}
+
+impl TryFrom<PaginationParams> for PageRequest {
+    type Error = PaginationError;
+
+    fn try_from(params: PaginationParams) -> Result<Self, Self::Error> {
+        PageRequest::new(
+            params.page.unwrap_or(1),
+            params.per_page.unwrap_or(DEFAULT_PER_PAGE),
+        )
+    }
+}
+
+/// Position after the last post of a page, in the `(pinned, date, id)` order
+/// used by the post list. Encoded as an opaque string for use in URLs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub pinned: bool,
+    pub date: Date,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let date_format = format_description!("[year]-[month]-[day]");
+        let date = self.date.format(&date_format).unwrap_or_default();
+        let raw = format!("{}|{}|{}", self.pinned as u8, date, self.id);
+        raw.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn decode(cursor: &str) -> Result<Cursor, PaginationError> {
+        if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+            return Err(PaginationError::Cursor);
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| PaginationError::Cursor)?;
+        let raw = String::from_utf8(bytes).map_err(|_| PaginationError::Cursor)?;
+
+        let date_format = format_description!("[year]-[month]-[day]");
+        match raw.split('|').collect::<Vec<&str>>()[..] {
+            [pinned, date, id] => Ok(Cursor {
+                pinned: pinned == "1",
+                date: Date::parse(date, &date_format).map_err(|_| PaginationError::Cursor)?,
+                id: id.parse().map_err(|_| PaginationError::Cursor)?,
+            }),
+            _ => Err(PaginationError::Cursor),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub per_page: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub page_count: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, request: PageRequest, total: i64) -> Page<T> {
+        Page {
+            items,
+            page: request.page,
+            per_page: request.per_page,
+            total,
+            page_count: (total + request.per_page - 1) / request.per_page,
+        }
+    }
+
+    pub fn empty(request: PageRequest) -> Page<T> {
+        Page::new(vec![], request, 0)
+    }
+
+    /// Separates the items from the page metadata, e.g. to render them apart.
+    pub fn into_parts(self) -> (Vec<T>, Page<()>) {
+        let meta = Page {
+            items: vec![],
+            page: self.page,
+            per_page: self.per_page,
+            total: self.total,
+            page_count: self.page_count,
+        };
+        (self.items, meta)
+    }
+
+    pub fn prev_page(&self) -> Option<i64> {
+        match self.page {
+            1 => None,
+            // Pages past the end link back to the last page that has items
+            page => Some((page - 1).min(self.page_count.max(1))),
+        }
+    }
+
+    pub fn next_page(&self) -> Option<i64> {
+        (self.page < self.page_count).then_some(self.page + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request() {
+        let params = PaginationParams {
+            page: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(PageRequest::try_from(params), Err(PaginationError::Page(0)));
+        assert_eq!(
+            PageRequest::new(1, MAX_PER_PAGE + 1),
+            Err(PaginationError::PerPage(MAX_PER_PAGE + 1))
+        );
+        assert_eq!(
+            PageRequest::try_from(PaginationParams::default()),
+            Ok(PageRequest::default())
+        );
+        assert_eq!(PageRequest::new(3, 10).unwrap().offset(), 20);
+        assert_eq!(
+            PageRequest::new(i64::MAX, MAX_PER_PAGE),
+            Err(PaginationError::Page(i64::MAX))
+        );
+        let last = PageRequest::new(MAX_PAGE, MAX_PER_PAGE).unwrap();
+        assert_eq!(last.offset(), (MAX_PAGE - 1) * MAX_PER_PAGE);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let cursor = Cursor {
+            pinned: true,
+            date: Date::from_calendar_date(2024, time::Month::February, 13).unwrap(),
+            id: 12,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor));
+        assert_eq!(Cursor::decode("zz"), Err(PaginationError::Cursor));
+        assert_eq!(Cursor::decode("616263"), Err(PaginationError::Cursor));
+    }
+
+    #[test]
+    fn test_page() {
+        let request = PageRequest::new(2, 10).unwrap();
+        let page = Page::new(vec![(); 10], request, 25);
+        assert_eq!(page.page_count, 3);
+        assert_eq!(page.prev_page(), Some(1));
+        assert_eq!(page.next_page(), Some(3));
+
+        let last = Page::<()>::new(vec![], PageRequest::new(3, 10).unwrap(), 25);
+        assert_eq!(last.next_page(), None);
+
+        let beyond = Page::<()>::new(vec![], PageRequest::new(9, 10).unwrap(), 25);
+        assert_eq!(beyond.prev_page(), Some(3));
+        assert_eq!(Page::<()>::empty(PageRequest::default()).page_count, 0);
+    }
+}
@@ -0,0 +1,64 @@
+use std::env;
+
+use axum::http::{HeaderValue, Response};
+
+/// Routes that aren't pages worth indexing, whatever the environment.
+const DISALLOWED_PATHS: [&str; 4] = [
+    "/api/",
+    "/search/suggest",
+    "/subscribe/confirm/",
+    "/unsubscribe/",
+];
+
+pub fn is_production() -> bool {
+    env::var("ENV").is_ok_and(|env| env == "PROD")
+}
+
+/// Whether this runs as a deployed Lambda function, whatever `ENV` says.
+pub fn is_lambda() -> bool {
+    env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok()
+}
+
+pub fn robots_rules(production: bool, site_url: &str) -> String {
+    let mut lines = vec![String::from("User-agent: *")];
+    if production {
+        lines.push(String::from("Allow: /"));
+        lines.extend(
+            DISALLOWED_PATHS
+                .iter()
+                .map(|path| format!("Disallow: {}", path)),
+        );
+    } else {
+        lines.push(String::from("Disallow: /"));
+    }
+    lines.push(String::new());
+    lines.push(format!("Sitemap: {}/sitemap.xml", site_url));
+
+    lines.join("\n") + "\n"
+}
+
+/// Response mapper that keeps non-prod deployments out of search indexes.
+pub async fn noindex<B>(mut response: Response<B>) -> Response<B> {
+    response
+        .headers_mut()
+        .insert("X-Robots-Tag", HeaderValue::from_static("noindex"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_txt() {
+        let production = robots_rules(true, "https://example.com");
+        assert!(production.contains("Allow: /\n"));
+        assert!(production.contains("Disallow: /api/\n"));
+        assert!(production.contains("Disallow: /unsubscribe/\n"));
+        assert!(production.ends_with("Sitemap: https://example.com/sitemap.xml\n"));
+
+        let staging = robots_rules(false, "https://staging.example.com");
+        assert!(staging.contains("Disallow: /\n"));
+        assert!(!staging.contains("Allow: /\n"));
+    }
+}
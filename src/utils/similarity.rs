@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "and", "are", "because", "been", "but", "can", "could", "did",
+    "does", "for", "from", "had", "has", "have", "how", "into", "its", "just", "more", "most",
+    "not", "now", "one", "only", "other", "our", "out", "should", "some", "such", "than", "that",
+    "the", "their", "them", "then", "there", "these", "they", "this", "those", "through", "use",
+    "used", "using", "very", "was", "way", "were", "what", "when", "where", "which", "while",
+    "who", "will", "with", "would", "you", "your",
+];
+
+pub type TermVector = HashMap<String, f32>;
+
+/// Lowercased alphanumeric words of at least three characters, without stop words.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Builds a TF-IDF vector per document. Vectors are L2-normalised, so the
+/// cosine similarity of two vectors is their dot product.
+pub fn tfidf_vectors(documents: &[&str]) -> Vec<TermVector> {
+    let tokenized: Vec<Vec<String>> = documents.iter().map(|doc| tokenize(doc)).collect();
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for tokens in &tokenized {
+        let unique: HashSet<&str> = tokens.iter().map(String::as_str).collect();
+        for term in unique {
+            *document_frequency.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    let n_documents = documents.len() as f32;
+
+    tokenized
+        .iter()
+        .map(|tokens| {
+            let mut term_frequency: HashMap<&str, f32> = HashMap::new();
+            for term in tokens {
+                *term_frequency.entry(term.as_str()).or_insert(0.0) += 1.0;
+            }
+
+            let mut vector: TermVector = term_frequency
+                .into_iter()
+                .map(|(term, tf)| {
+                    let df = document_frequency[term] as f32;
+                    let idf = ((1.0 + n_documents) / (1.0 + df)).ln() + 1.0;
+                    (term.to_string(), tf * idf)
+                })
+                .collect();
+
+            let norm = vector.values().map(|w| w * w).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.values_mut().for_each(|w| *w /= norm);
+            }
+            vector
+        })
+        .collect()
+}
+
+pub fn cosine_similarity(a: &TermVector, b: &TermVector) -> f32 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, weight)| large.get(term).map(|other| weight * other))
+        .sum()
+}
+
+/// Jaccard index of two tag lists, ignoring case.
+pub fn tag_overlap(a: &[String], b: &[String]) -> f32 {
+    let a: HashSet<String> = a.iter().map(|tag| tag.to_lowercase()).collect();
+    let b: HashSet<String> = b.iter().map(|tag| tag.to_lowercase()).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("Deploying Rust on AWS Lambda, with the cargo-lambda CLI!");
+        assert_eq!(
+            tokens,
+            vec![
+                "deploying",
+                "rust",
+                "aws",
+                "lambda",
+                "cargo",
+                "lambda",
+                "cli"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        let vectors = tfidf_vectors(&[
+            "nix flakes make python environments reproducible",
+            "reproducible python environments with nix",
+            "split ergonomic keyboard with an ortholinear layout",
+        ]);
+        let similar = cosine_similarity(&vectors[0], &vectors[1]);
+        let different = cosine_similarity(&vectors[0], &vectors[2]);
+        assert!(similar > different);
+        assert!((cosine_similarity(&vectors[0], &vectors[0]) - 1.0).abs() < 1e-5);
+        assert_eq!(different, 0.0);
+    }
+
+    #[test]
+    fn test_tag_overlap() {
+        let a = vec!["devops".to_string(), "Nix".to_string()];
+        let b = vec!["nix".to_string(), "python".to_string()];
+        assert!((tag_overlap(&a, &b) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(tag_overlap(&[], &[]), 0.0);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use time::OffsetDateTime;
+
+use crate::crud::blog::Blog;
+use crate::utils::config::SiteConfig;
+use crate::utils::feed::{rfc3339, tag_url, FeedEntry};
+
+/// Protocol limit of URLs in a single sitemap, past which it's split and
+/// served through a sitemap index.
+pub const MAX_SITEMAP_URLS: usize = 50_000;
+
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<OffsetDateTime>,
+    pub changefreq: &'static str,
+    pub priority: &'static str,
+    pub images: Vec<String>,
+}
+
+impl SitemapUrl {
+    fn new(
+        site: &SiteConfig,
+        path: &str,
+        lastmod: Option<OffsetDateTime>,
+        changefreq: &'static str,
+        priority: &'static str,
+    ) -> SitemapUrl {
+        SitemapUrl {
+            loc: site.url(path),
+            lastmod,
+            changefreq,
+            priority,
+            images: vec![],
+        }
+    }
+
+    pub fn lastmod_w3c(&self) -> Option<String> {
+        self.lastmod.map(rfc3339)
+    }
+}
+
+/// Every indexable page: the static routes, posts, tag pages and archive
+/// pages, with `lastmod` taken from the posts they list.
+pub fn sitemap_urls(site: &SiteConfig, blogs: Vec<Blog>) -> Vec<SitemapUrl> {
+    let latest = blogs.iter().map(Blog::modified_at).max();
+
+    let mut tags: BTreeMap<String, OffsetDateTime> = BTreeMap::new();
+    let mut years: BTreeMap<i32, OffsetDateTime> = BTreeMap::new();
+    let mut months: BTreeMap<(i32, u8), OffsetDateTime> = BTreeMap::new();
+    for blog in &blogs {
+        let modified_at = blog.modified_at();
+        for tag in &blog.tags {
+            let lastmod = tags.entry(tag.clone()).or_insert(modified_at);
+            *lastmod = (*lastmod).max(modified_at);
+        }
+        let year = years.entry(blog.date.year()).or_insert(modified_at);
+        *year = (*year).max(modified_at);
+        let month = months
+            .entry((blog.date.year(), blog.date.month() as u8))
+            .or_insert(modified_at);
+        *month = (*month).max(modified_at);
+    }
+
+    let mut urls = vec![
+        SitemapUrl::new(site, "/", latest, "weekly", "1.0"),
+        SitemapUrl::new(site, "/blogs", latest, "weekly", "0.8"),
+        SitemapUrl::new(site, "/resume", None, "monthly", "0.7"),
+        SitemapUrl::new(site, "/radar", None, "monthly", "0.5"),
+        SitemapUrl::new(site, "/archive", latest, "weekly", "0.6"),
+    ];
+
+    urls.extend(blogs.into_iter().map(|blog| {
+        let entry = FeedEntry::from_blog(blog, site);
+        SitemapUrl {
+            loc: entry.url,
+            lastmod: Some(entry.updated),
+            changefreq: "monthly",
+            priority: "0.9",
+            images: entry.images,
+        }
+    }));
+
+    urls.extend(tags.into_iter().map(|(tag, lastmod)| SitemapUrl {
+        loc: tag_url(site, &tag),
+        lastmod: Some(lastmod),
+        changefreq: "weekly",
+        priority: "0.5",
+        images: vec![],
+    }));
+    urls.extend(years.into_iter().rev().map(|(year, lastmod)| {
+        SitemapUrl::new(
+            site,
+            &format!("/archive/{}", year),
+            Some(lastmod),
+            "monthly",
+            "0.5",
+        )
+    }));
+    urls.extend(months.into_iter().rev().map(|((year, month), lastmod)| {
+        SitemapUrl::new(
+            site,
+            &format!("/archive/{}/{}", year, month),
+            Some(lastmod),
+            "monthly",
+            "0.4",
+        )
+    }));
+
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use crate::crud::blog::test_blog;
+    use crate::utils::config::test_site;
+
+    use super::*;
+
+    #[test]
+    fn test_sitemap_urls() {
+        let blog = |id: i32, date, updated_at, tags: &[&str]| Blog {
+            body: String::from("![diagram](/assets/images/diagram.png)"),
+            date,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            updated_at,
+            ..test_blog(id)
+        };
+        let blogs = vec![
+            blog(
+                2,
+                date!(2024 - 02 - 13),
+                datetime!(2024-05-01 0:00 UTC),
+                &["web"],
+            ),
+            blog(
+                1,
+                date!(2023 - 12 - 15),
+                // Added before its date, as a scheduled post
+                datetime!(2023-12-10 9:00 UTC),
+                &["web", "data ops"],
+            ),
+        ];
+
+        let site = test_site();
+        let urls = sitemap_urls(&site, blogs);
+        let find = |path: &str| urls.iter().find(|url| url.loc == site.url(path)).unwrap();
+
+        assert_eq!(urls.len(), 5 + 2 + 2 + 2 + 2);
+        assert_eq!(find("/").lastmod, Some(datetime!(2024-05-01 0:00 UTC)));
+        assert_eq!(
+            find("/blog/1").lastmod,
+            Some(datetime!(2023-12-15 0:00 UTC))
+        );
+        assert_eq!(
+            find("/blog/1").images,
+            vec![site.url("/assets/images/diagram.png")]
+        );
+        assert_eq!(
+            find("/tags/web").lastmod,
+            Some(datetime!(2024-05-01 0:00 UTC))
+        );
+        assert!(urls.iter().any(|url| url.loc.ends_with("/tags/data%20ops")));
+        assert_eq!(
+            find("/archive/2023/12").lastmod,
+            Some(datetime!(2023-12-15 0:00 UTC))
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::crud::blog::Blog;
+
+pub const MAX_SUGGESTIONS: usize = 5;
+const MAX_PREFIX_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SuggestionKind {
+    Title,
+    Tag,
+    Radar,
+}
+
+impl SuggestionKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SuggestionKind::Title => "post",
+            SuggestionKind::Tag => "tag",
+            SuggestionKind::Radar => "radar",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub label: String,
+    pub href: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Radar {
+    entries: Vec<RadarEntry>,
+}
+
+#[derive(Deserialize)]
+struct RadarEntry {
+    label: String,
+}
+
+/// Maps every prefix of every word in a suggestion label to the best
+/// `MAX_SUGGESTIONS` completions, so a lookup is a single hash map access.
+pub struct PrefixIndex {
+    prefixes: HashMap<String, Vec<Suggestion>>,
+}
+
+impl PrefixIndex {
+    pub fn new(entries: Vec<Suggestion>) -> PrefixIndex {
+        // (matches mid-label, entry index) per prefix
+        let mut candidates: HashMap<String, Vec<(bool, usize)>> = HashMap::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            let label = entry.label.to_lowercase();
+            let mut seen = HashSet::new();
+
+            for start in word_starts(&label) {
+                let mut prefix = String::new();
+                for c in label[start..].chars().take(MAX_PREFIX_LEN) {
+                    prefix.push(c);
+                    if seen.insert(prefix.clone()) {
+                        candidates
+                            .entry(prefix.clone())
+                            .or_default()
+                            .push((start != 0, i));
+                    }
+                }
+            }
+        }
+
+        let prefixes = candidates
+            .into_iter()
+            .map(|(prefix, mut matches)| {
+                matches.sort_by_key(|&(mid_label, i)| {
+                    (mid_label, entries[i].kind, entries[i].label.len())
+                });
+                let suggestions = matches
+                    .into_iter()
+                    .take(MAX_SUGGESTIONS)
+                    .map(|(_, i)| entries[i].clone())
+                    .collect();
+                (prefix, suggestions)
+            })
+            .collect();
+
+        PrefixIndex { prefixes }
+    }
+
+    pub fn from_blogs(blogs: &[Blog]) -> PrefixIndex {
+        let mut entries: Vec<Suggestion> = blogs
+            .iter()
+            .map(|blog| Suggestion {
+                kind: SuggestionKind::Title,
+                label: blog.title.clone(),
+                href: Some(format!("/blog/{}", blog.id)),
+            })
+            .collect();
+
+        let tags: HashSet<&String> = blogs.iter().flat_map(|blog| &blog.tags).collect();
+        entries.extend(tags.into_iter().map(|tag| Suggestion {
+            kind: SuggestionKind::Tag,
+            label: tag.clone(),
+            href: None,
+        }));
+
+        let radar: Radar = serde_json::from_str(include_str!("../../assets/radar.json"))
+            .expect("assets/radar.json should be valid");
+        entries.extend(radar.entries.into_iter().map(|entry| Suggestion {
+            kind: SuggestionKind::Radar,
+            label: entry.label,
+            href: Some(String::from("/radar")),
+        }));
+
+        PrefixIndex::new(entries)
+    }
+
+    pub fn suggest(&self, query: &str) -> Vec<Suggestion> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let key: String = query.chars().take(MAX_PREFIX_LEN).collect();
+        self.prefixes
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(|s| key == query || s.label.to_lowercase().contains(&query))
+            .cloned()
+            .collect()
+    }
+}
+
+fn word_starts(label: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut previous_alphanumeric = false;
+    for (i, c) in label.char_indices() {
+        if c.is_alphanumeric() && !previous_alphanumeric {
+            starts.push(i);
+        }
+        previous_alphanumeric = c.is_alphanumeric();
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(kind: SuggestionKind, label: &str) -> Suggestion {
+        Suggestion {
+            kind,
+            label: label.to_string(),
+            href: None,
+        }
+    }
+
+    #[test]
+    fn test_suggest() {
+        let index = PrefixIndex::new(vec![
+            suggestion(SuggestionKind::Radar, "Rust"),
+            suggestion(SuggestionKind::Title, "WASM with Rust and Javascript"),
+            suggestion(SuggestionKind::Tag, "rust"),
+            suggestion(SuggestionKind::Title, "Rusty pipes"),
+        ]);
+
+        let labels: Vec<String> = index.suggest("RU").into_iter().map(|s| s.label).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Rusty pipes",
+                "rust",
+                "Rust",
+                "WASM with Rust and Javascript"
+            ]
+        );
+        assert_eq!(index.suggest("javas").len(), 1);
+        assert!(index.suggest("").is_empty());
+        assert!(index.suggest("python").is_empty());
+    }
+
+    #[test]
+    fn test_suggest_limit() {
+        let entries = (0..10)
+            .map(|i| suggestion(SuggestionKind::Tag, &format!("tag{}", i)))
+            .collect();
+        let index = PrefixIndex::new(entries);
+        assert_eq!(index.suggest("tag").len(), MAX_SUGGESTIONS);
+    }
+}
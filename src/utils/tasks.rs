@@ -0,0 +1,49 @@
+use lambda_http::lambda_runtime::{self, LambdaEvent};
+use lambda_http::{service_fn, Error};
+use serde_json::Value;
+
+use crate::utils::config::AppState;
+use crate::utils::indexnow::submit_due;
+use crate::utils::newsletter;
+use crate::utils::webmention::{verify_pending, WebmentionClient};
+
+const USAGE: &str = "usage: webrs tasks | webrs newsletter send --post <id>";
+
+/// Work that shouldn't hold up a request or a cold start.
+pub async fn run_tasks(state: &AppState) {
+    verify_pending(&state.pool, &WebmentionClient::new()).await;
+    submit_due(&state.pool, &state.site).await;
+    match &state.mailer {
+        Some(mailer) => {
+            newsletter::send_due(
+                &state.pool,
+                &state.site,
+                mailer.as_ref(),
+                &state.unsubscribe,
+            )
+            .await
+        }
+        None => println!("Emails are turned off, not sending newsletters"),
+    }
+}
+
+/// Runs the tasks on every invocation, for the function on a schedule.
+pub async fn run_scheduled(state: AppState) -> Result<(), Error> {
+    lambda_runtime::run(service_fn(|_: LambdaEvent<Value>| async {
+        run_tasks(&state).await;
+        Ok::<(), Error>(())
+    }))
+    .await
+}
+
+/// Runs `tasks` once, or `newsletter send --post <id>`.
+pub async fn command(state: &AppState, args: &[String]) -> Result<(), String> {
+    if let [tasks] = args {
+        if tasks == "tasks" {
+            run_tasks(state).await;
+            return Ok(());
+        }
+    }
+    let blog_id = newsletter::parse_command(args).ok_or(USAGE)?;
+    newsletter::send_post(state, blog_id).await
+}
@@ -0,0 +1,129 @@
+use std::env;
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::utils::config::SiteConfig;
+use crate::utils::robots::{is_lambda, is_production};
+
+const TOKEN_BYTES: usize = 32;
+// Only good enough for development, production and Lambda functions refuse
+// to start without UNSUBSCRIBE_SECRET
+const DEVELOPMENT_SECRET: &str = "development-unsubscribe-secret";
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A random token for links in emails. Hex, so it survives the lowercasing
+/// of paths.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// What gets stored of a token, so a leaked table doesn't leak working links.
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+/// Signs addresses into unsubscribe tokens, so every email can carry a
+/// working link without storing anything per email.
+pub struct UnsubscribeKey {
+    secret: Vec<u8>,
+}
+
+impl UnsubscribeKey {
+    pub fn new(secret: &[u8]) -> UnsubscribeKey {
+        UnsubscribeKey {
+            secret: secret.to_vec(),
+        }
+    }
+
+    /// The key from `UNSUBSCRIBE_SECRET`, which production and Lambda
+    /// functions require.
+    pub fn from_env() -> UnsubscribeKey {
+        match env::var("UNSUBSCRIBE_SECRET") {
+            Ok(secret) => UnsubscribeKey::new(secret.as_bytes()),
+            Err(_) if is_production() || is_lambda() => panic!("UNSUBSCRIBE_SECRET must be set"),
+            Err(_) => {
+                println!("UNSUBSCRIBE_SECRET isn't set, using the development secret");
+                UnsubscribeKey::new(DEVELOPMENT_SECRET.as_bytes())
+            }
+        }
+    }
+
+    fn mac(&self, email: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC takes keys of any length");
+        mac.update(b"unsubscribe:");
+        mac.update(email.as_bytes());
+        mac
+    }
+
+    /// The hex encoded address and its signature.
+    pub fn token(&self, email: &str) -> String {
+        let signature = self.mac(email).finalize().into_bytes();
+        format!("{}-{}", to_hex(email.as_bytes()), to_hex(&signature))
+    }
+
+    /// The address a token was signed for, if the signature holds.
+    pub fn verify(&self, token: &str) -> Option<String> {
+        let (email, signature) = token.split_once('-')?;
+        let email = String::from_utf8(from_hex(email)?).ok()?;
+        self.mac(&email)
+            .verify_slice(&from_hex(signature)?)
+            .ok()
+            .map(|_| email)
+    }
+
+    pub fn url(&self, site: &SiteConfig, email: &str) -> String {
+        site.url(&format!("/unsubscribe/{}", self.token(email)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token() {
+        let token = new_token();
+        assert_eq!(token.len(), 64);
+        assert_eq!(token, token.to_lowercase());
+        assert_ne!(token, new_token());
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe_key() {
+        let key = UnsubscribeKey::new(b"secret");
+        let token = key.token("reader@example.com");
+        assert_eq!(token, token.to_lowercase());
+        assert_eq!(key.verify(&token).as_deref(), Some("reader@example.com"));
+
+        let (email, signature) = token.split_once('-').unwrap();
+        let other = to_hex(b"other@example.com");
+        assert_eq!(key.verify(&format!("{}-{}", other, signature)), None);
+        assert_eq!(key.verify(email), None);
+        assert_eq!(key.verify("zz-zz"), None);
+        assert_eq!(UnsubscribeKey::new(b"other").verify(&token), None);
+    }
+}
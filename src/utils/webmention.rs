@@ -0,0 +1,365 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use hyper::client::connect::dns::Name;
+use regex::Regex;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{redirect, Url};
+use sqlx::PgPool;
+
+use crate::crud::webmention::Webmention;
+
+/// Sources are only read far enough to find the link.
+const MAX_SOURCE_BYTES: usize = 1_000_000;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+const VERIFY_BATCH: usize = 20;
+const MAX_TITLE_CHARS: usize = 200;
+
+static LINK: OnceLock<Regex> = OnceLock::new();
+static TITLE: OnceLock<Regex> = OnceLock::new();
+
+fn is_web_url(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https") && url.host_str().is_some()
+}
+
+fn same_site(host: &str, site_host: &str) -> bool {
+    host.trim_start_matches("www.")
+        .eq_ignore_ascii_case(site_host.trim_start_matches("www."))
+}
+
+/// Checks a webmention request against the receiver spec and returns the id
+/// of the post it mentions.
+pub fn validate(source: &str, target: &str, site_url: &str) -> Result<i32, &'static str> {
+    let source = Url::parse(source).map_err(|_| "source must be a URL")?;
+    let target = Url::parse(target).map_err(|_| "target must be a URL")?;
+    if !is_web_url(&source) || !is_web_url(&target) {
+        return Err("source and target must be http or https URLs");
+    }
+    if source == target {
+        return Err("source and target must differ");
+    }
+
+    let site = Url::parse(site_url).map_err(|_| "site URL is misconfigured")?;
+    let on_site = match (target.host_str(), site.host_str()) {
+        (Some(host), Some(site_host)) => same_site(host, site_host),
+        _ => false,
+    };
+    let mut segments = target.path_segments().into_iter().flatten();
+    let id = match (segments.next(), segments.next(), segments.next()) {
+        (Some("blog"), Some(id), None | Some("")) => id.parse::<i32>().ok(),
+        _ => None,
+    };
+    match id {
+        Some(id) if on_site => Ok(id),
+        _ => Err("target must be a post on this site"),
+    }
+}
+
+/// Whether `html` links to `target`, with an exact match as the spec asks.
+pub fn links_to(html: &str, target: &str) -> bool {
+    LINK.get_or_init(|| Regex::new(r#"(?i)\s(?:href|src)\s*=\s*["']([^"']*)["']"#).unwrap())
+        .captures_iter(html)
+        .any(|captures| captures[1].trim().replace("&amp;", "&") == target)
+}
+
+pub fn page_title(html: &str) -> Option<String> {
+    let captures = TITLE
+        .get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap())
+        .captures(html)?;
+    let title = captures[1]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    (!title.is_empty()).then_some(title)
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            let shared = first == 100 && second & 0xc0 == 64;
+            !(first == 0
+                || shared
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let unique_local = ip.segments()[0] & 0xfe00 == 0xfc00;
+                let link_local = ip.segments()[0] & 0xffc0 == 0xfe80;
+                !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+            }
+        },
+    }
+}
+
+/// Whether the URL's host may be public. IP addresses are connected to
+/// without resolving, so they're checked here, names by [`PublicResolver`].
+fn is_public_host(url: &Url) -> bool {
+    match url.host_str().map(|host| host.trim_matches(['[', ']'])) {
+        Some(host) if host.eq_ignore_ascii_case("localhost") => false,
+        Some(host) => host.parse::<IpAddr>().map_or(true, is_public_ip),
+        None => false,
+    }
+}
+
+/// Why a source was refused, told apart from failures worth retrying.
+#[derive(Debug)]
+struct PrivateAddress(String);
+
+impl fmt::Display for PrivateAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is a private address", self.0)
+    }
+}
+
+impl Error for PrivateAddress {}
+
+fn is_private_address(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if err.is::<PrivateAddress>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Resolves names with the system resolver and refuses any that resolve to
+/// a loopback or private address, including on redirects.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if addrs.is_empty() || !addrs.iter().all(|addr| is_public_ip(addr.ip())) {
+                return Err(Box::new(PrivateAddress(name.as_str().to_string())) as _);
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Fetches webmention sources. It refuses hosts that are or resolve to
+/// loopback and private addresses, so a mention can't be used to reach
+/// internal services, unless tests opt in with
+/// [`WebmentionClient::allowing_private`].
+pub struct WebmentionClient {
+    client: reqwest::Client,
+    allow_private: bool,
+}
+
+impl WebmentionClient {
+    pub fn new() -> WebmentionClient {
+        WebmentionClient::build(false)
+    }
+
+    /// A client that also fetches from local servers.
+    #[cfg(test)]
+    pub fn allowing_private() -> WebmentionClient {
+        WebmentionClient::build(true)
+    }
+
+    fn build(allow_private: bool) -> WebmentionClient {
+        let policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if !allow_private && !is_public_host(attempt.url()) {
+                let host = attempt.url().host_str().unwrap_or_default().to_string();
+                attempt.error(PrivateAddress(host))
+            } else {
+                attempt.follow()
+            }
+        });
+        let mut builder = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .redirect(policy)
+            .user_agent("webrs-webmention");
+        if !allow_private {
+            // A proxy would resolve the source instead of the resolver
+            builder = builder.no_proxy().dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = builder.build().expect("Failed to build webmention client");
+        WebmentionClient {
+            client,
+            allow_private,
+        }
+    }
+
+    /// The source document, or `None` when it's gone or refuses to be read.
+    /// Errors are for failures worth retrying.
+    pub async fn fetch_source(&self, source: &str) -> Result<Option<String>, String> {
+        let url = Url::parse(source).map_err(|err| err.to_string())?;
+        if !self.allow_private && !is_public_host(&url) {
+            return Ok(None);
+        }
+
+        let mut response = match self.client.get(url).send().await {
+            Ok(response) => response,
+            Err(err) if is_private_address(&err) => return Ok(None),
+            Err(err) => return Err(err.to_string()),
+        };
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(format!("source responded with {}", status));
+        }
+        if !status.is_success() {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_SOURCE_BYTES {
+                body.truncate(MAX_SOURCE_BYTES);
+                break;
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+    }
+}
+
+/// Verifies a batch of queued mentions. Mentions that fail for transient
+/// reasons stay queued for the next run.
+pub async fn verify_pending(pool: &PgPool, client: &WebmentionClient) {
+    for _ in 0..VERIFY_BATCH {
+        match Webmention::claim_next(pool).await {
+            Ok(Some(mention)) => verify(pool, client, &mention).await,
+            Ok(None) => return,
+            Err(err) => {
+                eprintln!("Error claiming pending webmention: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+/// Checks that a mention's source links to its target, and marks it verified
+/// or rejected accordingly.
+pub async fn verify(pool: &PgPool, client: &WebmentionClient, mention: &Webmention) {
+    let result = match client.fetch_source(&mention.source).await {
+        Ok(Some(html)) if links_to(&html, &mention.target) => {
+            println!("Verified webmention {} from {}", mention.id, mention.source);
+            Webmention::mark_verified(pool, mention.id, page_title(&html).as_deref()).await
+        }
+        Ok(_) => {
+            println!("Rejected webmention {} from {}", mention.id, mention.source);
+            Webmention::mark_rejected(pool, mention.id).await
+        }
+        Err(err) => {
+            eprintln!(
+                "Error fetching webmention source {}: {}",
+                mention.source, err
+            );
+            Webmention::release(pool, mention.id).await
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("Error updating webmention {}: {}", mention.id, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{response::Html, routing::get, Router};
+
+    use super::*;
+
+    const SITE: &str = "https://www.example.com";
+
+    #[test]
+    fn test_validate() {
+        let source = "https://blog.example.org/post";
+        assert_eq!(
+            validate(source, "https://www.example.com/blog/4", SITE),
+            Ok(4)
+        );
+        assert_eq!(validate(source, "https://example.com/blog/4/", SITE), Ok(4));
+        assert!(validate(source, "https://www.example.com/blogs", SITE).is_err());
+        assert!(validate(source, "https://other.com/blog/4", SITE).is_err());
+        assert!(validate("ftp://example.org", "https://www.example.com/blog/4", SITE).is_err());
+        assert!(validate("not a url", "https://www.example.com/blog/4", SITE).is_err());
+        assert!(validate(
+            "https://www.example.com/blog/4",
+            "https://www.example.com/blog/4",
+            SITE
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_public_addresses() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(is_public_host(&url("https://93.184.215.14/")));
+        assert!(is_public_host(&url("https://blog.example.org/")));
+        assert!(!is_public_host(&url("http://169.254.169.254/latest")));
+        assert!(!is_public_host(&url("http://10.0.0.1/")));
+        assert!(!is_public_host(&url("http://[::ffff:127.0.0.1]/")));
+        assert!(!is_public_host(&url("http://[fd00::1]/")));
+        assert!(!is_public_host(&url("http://LOCALHOST:3000/")));
+
+        // Names are checked once resolved
+        let name = "localhost".parse::<Name>().unwrap();
+        let err = PublicResolver.resolve(name).await.err().unwrap();
+        assert!(is_private_address(err.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_source() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route(
+                "/post",
+                get(|| async {
+                    Html(
+                        "<title>\n  Notes &amp; links\n</title>\
+                        <a class=\"u-in-reply-to\" href=\"https://www.example.com/blog/4\">nice</a>",
+                    )
+                }),
+            )
+            .route(
+                "/gone",
+                get(|| async { axum::http::StatusCode::GONE }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = WebmentionClient::allowing_private();
+        let html = client
+            .fetch_source(&format!("http://{}/post", address))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(links_to(&html, "https://www.example.com/blog/4"));
+        assert!(!links_to(&html, "https://www.example.com/blog/5"));
+        assert_eq!(page_title(&html).as_deref(), Some("Notes & links"));
+
+        let gone = client
+            .fetch_source(&format!("http://{}/gone", address))
+            .await;
+        assert_eq!(gone, Ok(None));
+
+        // Private addresses are refused by default
+        let public_only = WebmentionClient::new();
+        let refused = public_only
+            .fetch_source(&format!("http://{}/post", address))
+            .await;
+        assert_eq!(refused, Ok(None));
+    }
+}